@@ -1,153 +1,2362 @@
+use crate::csr::CsrGraph;
+use crate::csv_field;
 use crate::text_extractor::TextExtractor;
 use bimap::BiMap;
 use bit_vec::BitVec;
 use parse_wiki_text::{DefinitionListItem, ListItem, Node, Output};
+use rand::rngs::StdRng;
+use rand::seq::index::sample;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
 use regex::{Regex, RegexBuilder};
-use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
 use collecting_hashmap::CollectingHashMap;
 use std::error::Error;
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 pub type Nd = usize;
 pub type Ed = (Nd, Nd);
 
-#[derive(Default, Debug)]
+/// Header of files written by `Graph::save_to_file`.
+const GRAPH_FILE_MAGIC: &[u8; 10] = b"WIKIGRAPH\x00";
+const GRAPH_FILE_VERSION: u32 = 5;
+
+/// Graphs bigger than that are not converted to matrix form.
+const MAX_MATRIX_SIZE: usize = 1000;
+
+/// Limit of paths returned by `Graph::enumerate_simple_paths`.
+const MAX_ENUMERATED_PATHS: usize = 10_000;
+
+#[derive(Default, Debug, Serialize, Deserialize)]
 pub struct Graph {
-    pub node_data: Vec<NodeData>,
+    node_data: Vec<NodeData>,
+    /// Arbitrary metadata of nodes (e.g. `"article_count"`), parallel to `node_data`.
+    node_attributes: Vec<HashMap<String, String>>,
     node_labels: BiMap<Nd, (String, bool)>,
-    edge_labels: HashMap<Ed, String>,
+    edge_labels: HashMap<Ed, Vec<String>, ahash::RandomState>,
+    /// Position of edge in an ordered list it was defined in (starting from 1).
+    edge_order: HashMap<Ed, u32, ahash::RandomState>,
+    edge_count: usize,
+    multi: bool,
+    #[serde(skip)]
+    generation: u64,
+    #[serde(skip)]
+    csr_cache: RefCell<CsrCache>,
+}
+
+/// CSR copies of adjacency lists made by walks, with graph generation they were made for.
+#[derive(Default, Debug)]
+struct CsrCache {
+    outgoing: Option<(u64, Rc<CsrGraph>)>,
+    incoming: Option<(u64, Rc<CsrGraph>)>,
+}
+
+/// Node id remembered together with graph generation, so that it's not used
+/// after the graph was changed (and nodes possibly renumbered).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StableNd(Nd, u64);
+
+impl StableNd {
+    /// Node id or `None` if the graph was changed since this reference was made.
+    pub fn deref(&self, graph: &Graph) -> Option<Nd> {
+        if self.1 == graph.generation {
+            Some(self.0)
+        } else {
+            None
+        }
+    }
+}
+
+/// Visited nodes, edges cut to break loops, discovery/finish times of DFS walk
+/// and classification of all edges followed by the walk.
+pub type DfsResult = (
+    BitVec,
+    Vec<CycleEvent>,
+    Vec<(usize, usize)>,
+    HashMap<Ed, EdgeType>,
+);
+
+/// Kind of edge found by DFS walk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeType {
+    /// Edge to a node discovered through it.
+    Tree,
+    /// Edge to an ancestor on current path (closes a loop).
+    Back,
+    /// Edge to an already finished descendant.
+    Forward,
+    /// Edge to an already finished node in a different subtree.
+    Cross,
+}
+
+/// Lazy DFS walk over nodes reachable from a start node, see `Graph::dfs_iter`.
+///
+/// Every node is yielded once; children are visited in order of outgoing edges.
+pub struct DfsIter<'a> {
+    graph: &'a Graph,
+    stack: Vec<(Nd, usize)>, // (node, children_visited)
+    visited: BitVec,
+    post_order: bool,
+}
+
+impl<'a> DfsIter<'a> {
+    fn new(graph: &'a Graph, start: Nd, post_order: bool) -> DfsIter<'a> {
+        graph.assert_valid_vertex(start);
+        let mut visited = BitVec::from_elem(graph.len(), false);
+        visited.set(start, true);
+        DfsIter {
+            graph,
+            stack: vec![(start, 0)],
+            visited,
+            post_order,
+        }
+    }
+}
+
+impl Iterator for DfsIter<'_> {
+    type Item = Nd;
+
+    fn next(&mut self) -> Option<Nd> {
+        loop {
+            let (node, children_visited) = self.stack.pop()?;
+            let children = &self.graph.node_data[node].outgoing;
+            if children_visited < children.len() {
+                self.stack.push((node, children_visited + 1));
+                let child = children[children_visited];
+                if !self.visited.get(child).unwrap() {
+                    self.visited.set(child, true);
+                    self.stack.push((child, 0));
+                }
+            }
+            if self.post_order {
+                if children_visited >= children.len() {
+                    return Some(node);
+                }
+            } else if children_visited == 0 {
+                return Some(node);
+            }
+        }
+    }
+}
+
+/// Lazy BFS walk yielding nodes reachable from a start node with their depth,
+/// see `Graph::bfs_iter`.
+pub struct BfsIter<'a> {
+    graph: &'a Graph,
+    queue: VecDeque<(Nd, usize)>,
+    visited: BitVec,
+    reversed: bool,
+}
+
+impl<'a> BfsIter<'a> {
+    fn new(graph: &'a Graph, start: Nd, reversed: bool) -> BfsIter<'a> {
+        graph.assert_valid_vertex(start);
+        let mut visited = BitVec::from_elem(graph.len(), false);
+        visited.set(start, true);
+        BfsIter {
+            graph,
+            queue: VecDeque::from(vec![(start, 0)]),
+            visited,
+            reversed,
+        }
+    }
+}
+
+impl Iterator for BfsIter<'_> {
+    type Item = (Nd, usize);
+
+    fn next(&mut self) -> Option<(Nd, usize)> {
+        let (node, depth) = self.queue.pop_front()?;
+        let data = &self.graph.node_data[node];
+        let neighbours = if self.reversed {
+            &data.incoming
+        } else {
+            &data.outgoing
+        };
+        for next in neighbours {
+            if !self.visited.get(*next).unwrap() {
+                self.visited.set(*next, true);
+                self.queue.push_back((*next, depth + 1));
+            }
+        }
+        Some((node, depth))
+    }
+}
+
+/// Edge closing a loop, found (and cut) while walking the graph.
+#[derive(Debug, Serialize)]
+pub struct CycleEvent {
+    pub from_label: String,
+    pub to_label: String,
+    pub from_id: Nd,
+    pub to_id: Nd,
+}
+
+/// Summary metrics of a graph, see `Graph::statistics`.
+#[derive(Debug, Serialize)]
+pub struct GraphStatistics {
+    pub node_count: usize,
+    pub edge_count: usize,
+    pub root_label: String,
+    pub max_depth: usize,
+    pub average_depth: f64,
+    pub category_count: usize,
+    pub article_count: usize,
+    pub cycle_count: usize,
+    pub top_out_degree: Vec<(String, usize)>,
+}
+
+#[derive(Default, Debug, Serialize, Deserialize)]
+pub struct NodeData {
+    pub outgoing: Vec<usize>,
+    pub incoming: Vec<usize>,
+}
+
+#[derive(Debug)]
+pub enum GraphFileError {
+    NotAGraphFile,
+    UnsupportedVersion(u32),
+}
+
+impl Display for GraphFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphFileError::NotAGraphFile => write!(f, "Not a graph file!"),
+            GraphFileError::UnsupportedVersion(v) => write!(
+                f,
+                "Unsupported graph file version {} (expected {})",
+                v, GRAPH_FILE_VERSION
+            ),
+        }
+    }
+}
+
+impl Error for GraphFileError {}
+
+#[derive(Debug)]
+pub struct DotParseError(String);
+
+impl Display for DotParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DOT parse error: {}", self.0)
+    }
 }
 
-#[derive(Default, Debug)]
-pub struct NodeData {
-    pub outgoing: Vec<usize>,
-    pub incoming: Vec<usize>,
-}
+impl Error for DotParseError {}
+
+#[derive(Debug)]
+pub struct CsvParseError(String);
+
+impl Display for CsvParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CSV parse error: {}", self.0)
+    }
+}
+
+impl Error for CsvParseError {}
+
+/// Graph has a loop, so nodes can't be put in topological order.
+#[derive(Debug)]
+pub struct CycleError {
+    /// Nodes that were not visited because they are on (or above) a loop.
+    pub unvisited: Vec<Nd>,
+}
+
+impl Display for CycleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Graph has a cycle, {} nodes were not visited",
+            self.unvisited.len()
+        )
+    }
+}
+
+impl Error for CycleError {}
+
+impl Graph {
+    pub fn len(&self) -> usize {
+        self.node_data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.node_data.is_empty()
+    }
+
+    /// Empty graph with space reserved for `n` vertices.
+    pub fn with_capacity(n: usize) -> Graph {
+        Graph {
+            node_data: Vec::with_capacity(n),
+            node_attributes: Vec::with_capacity(n),
+            node_labels: BiMap::with_capacity(n),
+            ..Default::default()
+        }
+    }
+
+    /// Empty graph allowing many edges (with different labels) between the same vertices.
+    pub fn new_multigraph() -> Graph {
+        Graph {
+            multi: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_multigraph(&self) -> bool {
+        self.multi
+    }
+
+    /// Counter incremented on every change of vertices or edges.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Reference to node `id` valid until the graph is changed.
+    pub fn stable(&self, id: Nd) -> StableNd {
+        self.assert_valid_vertex(id);
+        StableNd(id, self.generation)
+    }
+
+    pub fn has_vertex(&self, id: Nd) -> bool {
+        id < self.node_data.len()
+    }
+
+    pub fn assert_valid_vertex(&self, id: Nd) {
+        assert!(
+            self.has_vertex(id),
+            "Vertex {} doesn't exist in graph of {} vertices",
+            id,
+            self.len()
+        );
+    }
+
+    pub fn add_vertex(&mut self, label: (String, bool)) -> Nd {
+        let new_idx = self.node_data.len();
+        self.node_data.push(NodeData::default());
+        self.node_attributes.push(HashMap::new());
+        self.generation += 1;
+        self.node_labels.insert(new_idx, label);
+        new_idx
+    }
+
+    /// Add edge (or replace label of existing one).
+    ///
+    /// In a multigraph another edge is added between the same vertices instead.
+    ///
+    /// # Return value
+    /// `true` if a new edge was created.
+    pub fn add_edge(&mut self, e: Ed, label: String) -> bool {
+        let (l, r) = e;
+        if !self.has_vertex(l) || !self.has_vertex(r) {
+            return false;
+        }
+        match self.edge_labels.get_mut(&e) {
+            Some(labels) if self.multi => labels.push(label),
+            Some(labels) => {
+                labels[0] = label;
+                return false;
+            }
+            None => {
+                self.edge_labels.insert(e, vec![label]);
+                self.node_data[l].outgoing.push(r);
+                self.node_data[r].incoming.push(l);
+            }
+        }
+        self.edge_count += 1;
+        self.generation += 1;
+        true
+    }
+
+    /// Remove edge (all edges between the same vertices in a multigraph).
+    ///
+    /// # Return value
+    /// Label of the removed edge or `None` if there was no such edge.
+    pub fn remove_edge(&mut self, e: &Ed) -> Option<String> {
+        self.take_edge(e)
+            .map(|labels| labels.into_iter().next().unwrap())
+    }
+
+    /// Remove edge returning all its labels.
+    fn take_edge(&mut self, e: &Ed) -> Option<Vec<String>> {
+        let labels = self.edge_labels.remove(e)?;
+        self.edge_order.remove(e);
+        let (l, r) = *e;
+        self.node_data[l].outgoing.retain(|n| *n != r);
+        self.node_data[r].incoming.retain(|n| *n != l);
+        self.edge_count -= labels.len();
+        self.generation += 1;
+        Some(labels)
+    }
+
+    /// Keep only edges `(from, to, label)` for which `predicate` returns `true`.
+    pub fn retain_edges<F>(&mut self, predicate: F)
+    where
+        F: Fn(Nd, Nd, &str) -> bool,
+    {
+        let mut changed: Vec<(Ed, Vec<String>)> = Vec::new();
+        for ((from, to), labels) in &self.edge_labels {
+            let kept: Vec<String> = labels
+                .iter()
+                .filter(|label| predicate(*from, *to, label))
+                .cloned()
+                .collect();
+            if kept.len() != labels.len() {
+                changed.push(((*from, *to), kept));
+            }
+        }
+        for (e, kept) in changed {
+            if kept.is_empty() {
+                self.take_edge(&e);
+            } else {
+                self.edge_count -= self.edge_labels[&e].len() - kept.len();
+                self.generation += 1;
+                self.edge_labels.insert(e, kept);
+            }
+        }
+    }
+
+    /// Flip direction of edge `(from, to)` keeping its label.
+    ///
+    /// # Return value
+    /// `true` if the edge existed.
+    pub fn reverse_edge(&mut self, from: Nd, to: Nd) -> bool {
+        let order = self.get_edge_order(&(from, to));
+        match self.take_edge(&(from, to)) {
+            Some(labels) => {
+                for label in labels {
+                    self.add_edge((to, from), label);
+                }
+                if let Some(order) = order {
+                    self.set_edge_order((to, from), order);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edge_count
+    }
+
+    /// Keep only vertices for which `keep` returns `true`, dropping edges of removed ones.
+    ///
+    /// Remaining vertices are renumbered, preserving their order.
+    ///
+    /// # Return value
+    /// Mapping from old to new vertex ids (`None` for removed vertices).
+    pub fn retain_vertices<F>(&mut self, mut keep: F) -> Vec<Option<Nd>>
+    where
+        F: FnMut(Nd) -> bool,
+    {
+        let mut mapping = Vec::with_capacity(self.len());
+        let mut next = 0;
+        for n in 0..self.len() {
+            if keep(n) {
+                mapping.push(Some(next));
+                next += 1;
+            } else {
+                mapping.push(None);
+            }
+        }
+
+        let old_data = std::mem::take(&mut self.node_data);
+        for (n, data) in old_data.into_iter().enumerate() {
+            if mapping[n].is_some() {
+                self.node_data.push(NodeData {
+                    outgoing: data.outgoing.iter().filter_map(|x| mapping[*x]).collect(),
+                    incoming: data.incoming.iter().filter_map(|x| mapping[*x]).collect(),
+                });
+            }
+        }
+        let old_attributes = std::mem::take(&mut self.node_attributes);
+        self.node_attributes = old_attributes
+            .into_iter()
+            .enumerate()
+            .filter(|(n, _)| mapping[*n].is_some())
+            .map(|(_, attributes)| attributes)
+            .collect();
+        let old_labels = std::mem::take(&mut self.node_labels);
+        for (n, label) in old_labels {
+            if let Some(new) = mapping[n] {
+                self.node_labels.insert(new, label);
+            }
+        }
+        let old_edges = std::mem::take(&mut self.edge_labels);
+        self.edge_labels = old_edges
+            .into_iter()
+            .filter_map(|((l, r), label)| Some(((mapping[l]?, mapping[r]?), label)))
+            .collect();
+        self.edge_count = self.edge_labels.values().map(|labels| labels.len()).sum();
+        let old_order = std::mem::take(&mut self.edge_order);
+        self.edge_order = old_order
+            .into_iter()
+            .filter_map(|((l, r), order)| Some(((mapping[l]?, mapping[r]?), order)))
+            .collect();
+        self.generation += 1;
+        debug_assert_eq!(self.verify_invariants(), Ok(()));
+
+        mapping
+    }
+
+    /// Check consistency of internal structures.
+    ///
+    /// # Return value
+    /// Descriptions of all found violations.
+    pub fn verify_invariants(&self) -> Result<(), Vec<String>> {
+        let n = self.len();
+        let mut violations = Vec::new();
+        if self.node_labels.len() != n {
+            violations.push(format!("{} labels for {} nodes", self.node_labels.len(), n));
+        }
+        if self.node_attributes.len() != n {
+            violations.push(format!(
+                "{} attribute maps for {} nodes",
+                self.node_attributes.len(),
+                n
+            ));
+        }
+        for (u, data) in self.node_data.iter().enumerate() {
+            for v in data.outgoing.iter().chain(data.incoming.iter()) {
+                if *v >= n {
+                    violations.push(format!("node {} refers to missing node {}", u, v));
+                }
+            }
+            for v in &data.outgoing {
+                if !self.edge_labels.contains_key(&(u, *v)) {
+                    violations.push(format!("edge ({}, {}) has no label", u, v));
+                }
+            }
+        }
+        for (u, v) in self.edge_labels.keys() {
+            if *u >= n || *v >= n {
+                violations.push(format!("edge ({}, {}) refers to missing node", u, v));
+                continue;
+            }
+            if !self.node_data[*u].outgoing.contains(v) {
+                violations.push(format!("edge ({}, {}) missing in outgoing of {}", u, v, u));
+            }
+            if !self.node_data[*v].incoming.contains(u) {
+                violations.push(format!("edge ({}, {}) missing in incoming of {}", u, v, v));
+            }
+        }
+        let labels: usize = self.edge_labels.values().map(|labels| labels.len()).sum();
+        if labels != self.edge_count {
+            violations.push(format!(
+                "edge count is {}, but there are {} labels",
+                self.edge_count, labels
+            ));
+        }
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Remove vertices having no edges.
+    ///
+    /// # Return value
+    /// Number of removed nodes.
+    pub fn remove_isolated_nodes(&mut self) -> usize {
+        let before = self.len();
+        let keep: Vec<bool> = self
+            .node_data
+            .iter()
+            .map(|d| !d.incoming.is_empty() || !d.outgoing.is_empty())
+            .collect();
+        self.retain_vertices(|n| keep[n]);
+        before - self.len()
+    }
+
+    /// Remove all nodes that can't be reached from `root`.
+    ///
+    /// # Return value
+    /// Mapping from old to new vertex ids (`None` for removed vertices).
+    pub fn prune_unreachable(&mut self, root: Nd) -> Vec<Option<Nd>> {
+        self.assert_valid_vertex(root);
+        let distances = self.bfs_distances(root, false, usize::MAX);
+        self.retain_vertices(|n| distances[n].is_some())
+    }
+
+    /// Collapse chains of nodes having exactly one parent and one child.
+    ///
+    /// Edge `p -> m -> c` is replaced with `p -> c` labelled with names of both
+    /// edges joined with `"/"` and node `m` is removed. Node `keep` (e.g. the
+    /// root of a walk) is never removed.
+    ///
+    /// # Return value
+    /// Number of removed nodes.
+    pub fn contract_paths(&mut self, keep: Option<Nd>) -> usize {
+        let mut removed = BitVec::from_elem(self.len(), false);
+        for m in 0..self.len() {
+            if Some(m) == keep {
+                continue;
+            }
+            let data = &self.node_data[m];
+            if data.incoming.len() != 1 || data.outgoing.len() != 1 {
+                continue;
+            }
+            let (p, c) = (data.incoming[0], data.outgoing[0]);
+            if p == m || c == m || p == c || self.edge_labels.contains_key(&(p, c)) {
+                continue;
+            }
+            let label = format!("{}/{}", self.edge_name(p, m), self.edge_name(m, c));
+            self.remove_edge(&(p, m));
+            self.remove_edge(&(m, c));
+            self.add_edge((p, c), label);
+            removed.set(m, true);
+        }
+        let count = removed.iter().filter(|r| *r).count();
+        if count > 0 {
+            self.retain_vertices(|n| !removed.get(n).unwrap());
+        }
+        count
+    }
+
+    /// Edge label or, when it's empty, label of the target vertex.
+    fn edge_name(&self, from: Nd, to: Nd) -> &str {
+        let label = self.get_edge_label(&(from, to));
+        if label.is_empty() {
+            &self.get_vertex_label(to).0
+        } else {
+            label
+        }
+    }
+
+    /// Add edge between vertices with given labels, creating vertices if needed.
+    ///
+    /// # Return value
+    /// Flags telling if `(vtx1, edge, vtx2)` were created.
+    pub fn add(
+        &mut self,
+        vtx1: (String, bool),
+        edge: String,
+        vtx2: (String, bool),
+    ) -> (bool, bool, bool) {
+        let len = self.len();
+        let v1 = self.find_or_add_vertex(vtx1);
+        let v2 = self.find_or_add_vertex(vtx2);
+        let edge_created = self.add_edge((v1, v2), edge);
+        (v1 >= len, edge_created, v2 >= len)
+    }
+
+    pub fn find_vertex(&self, label: &(String, bool)) -> Option<Nd> {
+        self.node_labels.get_by_right(&label).map(|x| *x)
+    }
+
+    pub fn find_all_vertices<F>(&self, predicate: F) -> Vec<Nd>
+    where
+        F: Fn(&(String, bool)) -> bool,
+    {
+        self.node_labels_iter()
+            .filter(|(_, label)| predicate(label))
+            .map(|(n, _)| n)
+            .collect()
+    }
+
+    pub fn find_vertices_by_prefix(&self, prefix: &str) -> Vec<Nd> {
+        self.find_all_vertices(|(label, _)| label.starts_with(prefix))
+    }
+
+    /// Find vertices with label at most `max_distance` edits away from `query`.
+    ///
+    /// # Return value
+    /// Nodes with their edit distance, closest first.
+    pub fn find_vertices_fuzzy(
+        &self,
+        query: &str,
+        is_category: bool,
+        max_distance: usize,
+    ) -> Vec<(Nd, usize)> {
+        let query: Vec<char> = query.chars().collect();
+        let mut result: Vec<(Nd, usize)> = self
+            .node_labels_iter()
+            .filter(|(_, (_, category))| *category == is_category)
+            .filter_map(|(n, (label, _))| {
+                let distance = edit_distance(&query, label);
+                if distance <= max_distance {
+                    Some((n, distance))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        result.sort_by_key(|(n, distance)| (*distance, *n));
+        result
+    }
+
+    pub fn find_or_add_vertex(&mut self, label: (String, bool)) -> Nd {
+        if let Some(n) = self.find_vertex(&label) {
+            n
+        } else {
+            self.add_vertex(label)
+        }
+    }
+
+    pub fn roots(&self) -> Vec<Nd> {
+        let mut result = Vec::new();
+        for (i, n) in self.node_data.iter().enumerate() {
+            if n.incoming.is_empty() {
+                result.push(i);
+            }
+        }
+        result
+    }
+
+    pub fn get_node_data(&self, id: Nd) -> &NodeData {
+        &self.node_data[id]
+    }
+
+    /// Nodes having an edge to `n` (parent categories).
+    pub fn direct_parents(&self, n: Nd) -> &[Nd] {
+        &self.node_data[n].incoming
+    }
+
+    /// Nodes `n` has an edge to (subcategories and articles).
+    pub fn direct_children(&self, n: Nd) -> &[Nd] {
+        &self.node_data[n].outgoing
+    }
+
+    /// Iterate over `(id, data)` pairs in index order.
+    pub fn node_data_iter(&self) -> impl Iterator<Item = (Nd, &NodeData)> {
+        self.node_data.iter().enumerate()
+    }
+
+    /// Iterate over `(id, data)` pairs in index order, allowing changes.
+    ///
+    /// Edge labels and edge count are not updated, so adjacency lists should
+    /// only be reordered.
+    pub fn node_data_iter_mut(&mut self) -> impl Iterator<Item = (Nd, &mut NodeData)> {
+        self.generation += 1;
+        self.node_data.iter_mut().enumerate()
+    }
+
+    pub fn get_vertex_label(&self, id: Nd) -> &(String, bool) {
+        self.node_labels.get_by_left(&id).unwrap()
+    }
+
+    /// Copy edges with their labels from `other` graph.
+    ///
+    /// `node_map` maps node ids of `other` to node ids of this graph, edges with
+    /// unmapped ends are skipped. Labels of edges present in both graphs are
+    /// joined with `"|"` (unless this is a multigraph).
+    pub fn copy_edge_labels_from(&mut self, other: &Graph, node_map: &HashMap<Nd, Nd>) {
+        for ((l, r), labels) in &other.edge_labels {
+            if let (Some(l), Some(r)) = (node_map.get(l), node_map.get(r)) {
+                let e = (*l, *r);
+                for label in labels {
+                    match self.edge_labels.get_mut(&e) {
+                        Some(existing) if !self.multi => {
+                            let existing = &mut existing[0];
+                            if existing != label {
+                                existing.push('|');
+                                existing.push_str(label);
+                            }
+                        }
+                        _ => {
+                            self.add_edge(e, label.clone());
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Iterate over `(id, label)` pairs in index order.
+    pub fn node_labels_iter(&self) -> impl Iterator<Item = (Nd, &(String, bool))> {
+        (0..self.len()).map(move |n| (n, self.get_vertex_label(n)))
+    }
+
+    /// Label of edge (of the first one added, in a multigraph).
+    pub fn get_edge_label(&self, e: &Ed) -> &str {
+        &self.edge_labels.get(e).unwrap()[0]
+    }
+
+    /// Labels of all edges between given vertices.
+    pub fn get_edge_labels(&self, e: &Ed) -> &[String] {
+        self.edge_labels.get(e).unwrap()
+    }
+
+    pub fn set_node_attribute(&mut self, n: Nd, key: &str, value: String) {
+        self.assert_valid_vertex(n);
+        self.node_attributes[n].insert(key.to_string(), value);
+    }
+
+    pub fn get_node_attribute(&self, n: Nd, key: &str) -> Option<&str> {
+        self.node_attributes[n].get(key).map(|v| v.as_str())
+    }
+
+    /// Position of the edge in an ordered list, if it was defined in one.
+    pub fn get_edge_order(&self, e: &Ed) -> Option<u32> {
+        self.edge_order.get(e).copied()
+    }
+
+    /// Remember position of existing edge in an ordered list.
+    pub fn set_edge_order(&mut self, e: Ed, order: u32) {
+        if self.edge_labels.contains_key(&e) {
+            self.edge_order.insert(e, order);
+        }
+    }
+
+    /// Out-degrees of all nodes, sorted descending.
+    pub fn degree_sequence(&self) -> Vec<usize> {
+        let mut result: Vec<usize> = self.node_data.iter().map(|n| n.outgoing.len()).collect();
+        result.sort_unstable_by(|a, b| b.cmp(a));
+        result
+    }
+
+    /// In-degrees of all nodes, sorted descending.
+    pub fn in_degree_sequence(&self) -> Vec<usize> {
+        let mut result: Vec<usize> = self.node_data.iter().map(|n| n.incoming.len()).collect();
+        result.sort_unstable_by(|a, b| b.cmp(a));
+        result
+    }
+
+    /// Save graph in compact binary format.
+    pub fn save_to_file(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(GRAPH_FILE_MAGIC)?;
+        writer.write_all(&GRAPH_FILE_VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, self)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Load graph saved by `save_to_file`.
+    ///
+    /// Files with wrong header or format version are rejected.
+    pub fn load_from_file(path: &Path) -> Result<Graph, Box<dyn Error>> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; 10];
+        let mut version = [0u8; 4];
+        if reader.read_exact(&mut magic).is_err() || &magic != GRAPH_FILE_MAGIC {
+            return Err(Box::new(GraphFileError::NotAGraphFile));
+        }
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != GRAPH_FILE_VERSION {
+            return Err(Box::new(GraphFileError::UnsupportedVersion(version)));
+        }
+        Ok(bincode::deserialize_from(reader)?)
+    }
+
+    /// Dense adjacency matrix (`matrix[i][j]` tells if there's edge `(i, j)`).
+    ///
+    /// Returns `None` for graphs with more than `MAX_MATRIX_SIZE` nodes.
+    pub fn to_adjacency_matrix(&self) -> Option<Vec<Vec<bool>>> {
+        if self.len() > MAX_MATRIX_SIZE {
+            return None;
+        }
+        let mut matrix = vec![vec![false; self.len()]; self.len()];
+        for (l, data) in self.node_data.iter().enumerate() {
+            for r in &data.outgoing {
+                matrix[l][*r] = true;
+            }
+        }
+        Some(matrix)
+    }
+
+    /// Dense adjacency matrix with edge weights (`0.0` where there's no edge).
+    ///
+    /// Edges don't have weights yet, so every edge has weight `1.0`.
+    /// Returns `None` for graphs with more than `MAX_MATRIX_SIZE` nodes.
+    pub fn to_weighted_adjacency_matrix(&self) -> Option<Vec<Vec<f64>>> {
+        if self.len() > MAX_MATRIX_SIZE {
+            return None;
+        }
+        let mut matrix = vec![vec![0.0; self.len()]; self.len()];
+        for (l, data) in self.node_data.iter().enumerate() {
+            for r in &data.outgoing {
+                matrix[l][*r] = 1.0;
+            }
+        }
+        Some(matrix)
+    }
+
+    /// Write graph as GEXF 1.3 document (format used by Gephi).
+    pub fn export_gexf<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(w, r#"<gexf xmlns="http://gexf.net/1.3" version="1.3">"#)?;
+        writeln!(w, r#"  <graph defaultedgetype="directed">"#)?;
+        writeln!(w, r#"    <attributes class="node">"#)?;
+        writeln!(
+            w,
+            r#"      <attribute id="is_category" title="is_category" type="boolean"/>"#
+        )?;
+        for (id, type_) in GEXF_ATTRIBUTES {
+            writeln!(
+                w,
+                r#"      <attribute id="{0}" title="{0}" type="{1}"/>"#,
+                id, type_
+            )?;
+        }
+        writeln!(w, r#"    </attributes>"#)?;
+        writeln!(w, r#"    <nodes>"#)?;
+        for n in 0..self.len() {
+            let (label, is_category) = self.get_vertex_label(n);
+            writeln!(
+                w,
+                r#"      <node id="{}" label="{}">"#,
+                n,
+                xml_escape(label)
+            )?;
+            write!(
+                w,
+                r#"        <attvalues><attvalue for="is_category" value="{}"/>"#,
+                is_category
+            )?;
+            for (id, _) in GEXF_ATTRIBUTES {
+                if let Some(value) = self.get_node_attribute(n, id) {
+                    write!(
+                        w,
+                        r#"<attvalue for="{}" value="{}"/>"#,
+                        id,
+                        xml_escape(value)
+                    )?;
+                }
+            }
+            writeln!(w, "</attvalues>")?;
+            writeln!(w, r#"      </node>"#)?;
+        }
+        writeln!(w, r#"    </nodes>"#)?;
+        writeln!(w, r#"    <edges>"#)?;
+        let mut id = 0;
+        for (l, data) in self.node_data.iter().enumerate() {
+            for r in &data.outgoing {
+                for label in self.get_edge_labels(&(l, *r)) {
+                    writeln!(
+                        w,
+                        r#"      <edge id="{}" source="{}" target="{}" label="{}"/>"#,
+                        id,
+                        l,
+                        r,
+                        xml_escape(label)
+                    )?;
+                    id += 1;
+                }
+            }
+        }
+        writeln!(w, r#"    </edges>"#)?;
+        writeln!(w, r#"  </graph>"#)?;
+        writeln!(w, r#"</gexf>"#)
+    }
+
+    /// Write graph in GML (Graph Modelling Language), read by yEd, Gephi and NetworkX.
+    ///
+    /// GML strings can't contain `"`, so labels are escaped with HTML entities.
+    pub fn export_gml<W: Write>(&self, w: &mut W) -> std::io::Result<()> {
+        writeln!(w, "graph [")?;
+        writeln!(w, "  directed 1")?;
+        for (n, (label, is_category)) in self.node_labels_iter() {
+            writeln!(
+                w,
+                "  node [ id {} label \"{}\" is_category {} ]",
+                n,
+                xml_escape(label),
+                *is_category as u8
+            )?;
+        }
+        for (l, data) in self.node_data.iter().enumerate() {
+            for r in &data.outgoing {
+                for label in self.get_edge_labels(&(l, *r)) {
+                    writeln!(
+                        w,
+                        "  edge [ source {} target {} label \"{}\" ]",
+                        l,
+                        r,
+                        xml_escape(label)
+                    )?;
+                }
+            }
+        }
+        writeln!(w, "]")
+    }
+
+    /// Write nodes (`id,label,is_category`) and edges (`from_id,to_id,label`) as CSV files.
+    ///
+    /// Edge having many labels is written once per label.
+    pub fn export_csv(&self, nodes_path: &Path, edges_path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut nodes = BufWriter::new(File::create(nodes_path)?);
+        writeln!(nodes, "id,label,is_category")?;
+        for (n, (label, is_category)) in self.node_labels_iter() {
+            writeln!(nodes, "{},{},{}", n, csv_field(label), is_category)?;
+        }
+        nodes.flush()?;
+
+        let mut edges = BufWriter::new(File::create(edges_path)?);
+        writeln!(edges, "from_id,to_id,label")?;
+        for (l, data) in self.node_data.iter().enumerate() {
+            for r in &data.outgoing {
+                for label in self.get_edge_labels(&(l, *r)) {
+                    writeln!(edges, "{},{},{}", l, r, csv_field(label))?;
+                }
+            }
+        }
+        edges.flush()?;
+        Ok(())
+    }
+
+    /// Convert to `petgraph::Graph` with the same node indices and labels.
+    #[cfg(feature = "petgraph")]
+    pub fn to_petgraph(&self) -> petgraph::Graph<(String, bool), String> {
+        let mut result = petgraph::Graph::with_capacity(self.len(), self.edge_count);
+        for (_, label) in self.node_labels_iter() {
+            result.add_node(label.clone());
+        }
+        for (l, data) in self.node_data.iter().enumerate() {
+            for r in &data.outgoing {
+                for label in self.get_edge_labels(&(l, *r)) {
+                    result.add_edge(
+                        petgraph::graph::NodeIndex::new(l),
+                        petgraph::graph::NodeIndex::new(*r),
+                        label.clone(),
+                    );
+                }
+            }
+        }
+        result
+    }
+
+    /// Convert from `petgraph::Graph`, keeping node indices.
+    ///
+    /// Multigraph is created if there are parallel edges. Node labels have to be unique.
+    #[cfg(feature = "petgraph")]
+    pub fn from_petgraph(g: petgraph::Graph<(String, bool), String>) -> Graph {
+        use petgraph::visit::EdgeRef;
+        let has_parallel_edges = g.node_indices().any(|n| {
+            let mut targets: Vec<_> = g.edges(n).map(|e| e.target()).collect();
+            targets.sort_unstable();
+            targets.windows(2).any(|w| w[0] == w[1])
+        });
+        let mut result = if has_parallel_edges {
+            Graph::new_multigraph()
+        } else {
+            Graph::default()
+        };
+        let (nodes, edges) = g.into_nodes_edges();
+        for node in nodes {
+            assert!(
+                result.find_vertex(&node.weight).is_none(),
+                "Duplicate node label {:?}",
+                node.weight
+            );
+            result.add_vertex(node.weight);
+        }
+        for edge in edges {
+            result.add_edge((edge.source().index(), edge.target().index()), edge.weight);
+        }
+        result
+    }
+
+    /// Build graph from `(from_label, from_is_category, edge_label, to_label, to_is_category)` tuples.
+    pub fn from_edges<I>(edges: I) -> Graph
+    where
+        I: IntoIterator<Item = (String, bool, String, String, bool)>,
+    {
+        let mut graph = Graph::default();
+        for (from, from_is_category, label, to, to_is_category) in edges {
+            let l = graph.find_or_add_vertex((from, from_is_category));
+            let r = graph.find_or_add_vertex((to, to_is_category));
+            graph.add_edge((l, r), label);
+        }
+        graph
+    }
+
+    /// Load graph from CSV lines `from_label,is_from_cat,edge_label,to_label,is_to_cat`
+    /// (booleans as `0`/`1`).
+    ///
+    /// Fields may be quoted with `"` (`""` inside quotes is a quote character).
+    /// Empty lines and header line starting with `from_label` are skipped.
+    pub fn load_from_csv<R: BufRead>(r: R) -> Result<Graph, Box<dyn Error>> {
+        let mut edges = Vec::new();
+        for (i, line) in r.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() || i == 0 && line.starts_with("from_label") {
+                continue;
+            }
+            let fields = split_csv_record(&line)
+                .ok_or_else(|| CsvParseError(format!("line {}: unterminated quote", i + 1)))?;
+            if fields.len() != 5 {
+                return Err(Box::new(CsvParseError(format!(
+                    "line {}: expected 5 fields, got {}",
+                    i + 1,
+                    fields.len()
+                ))));
+            }
+            let flag = |value: &str| match value.trim() {
+                "0" => Ok(false),
+                "1" => Ok(true),
+                other => Err(CsvParseError(format!(
+                    "line {}: expected 0 or 1, got '{}'",
+                    i + 1,
+                    other
+                ))),
+            };
+            let mut fields = fields.into_iter();
+            let from = fields.next().unwrap();
+            let is_from_cat = flag(&fields.next().unwrap())?;
+            let label = fields.next().unwrap();
+            let to = fields.next().unwrap();
+            let is_to_cat = flag(&fields.next().unwrap())?;
+            edges.push((from, is_from_cat, label, to, is_to_cat));
+        }
+        Ok(Graph::from_edges(edges))
+    }
+
+    /// Build graph from a minimal subset of Graphviz DOT language.
+    ///
+    /// Only `digraph` with node and edge statements is supported. Node `label`
+    /// attribute sets vertex label (node id is used when it's missing) and
+    /// `category="false"` marks articles. Edge `label` attribute sets edge label.
+    pub fn from_dot(dot_source: &str) -> Result<Graph, DotParseError> {
+        let mut tokens = DotTokens::new(dot_source);
+        match tokens.next_token()? {
+            Some(DotToken::Id(ref kw)) if kw == "digraph" => {}
+            Some(DotToken::Id(ref kw)) if kw == "strict" => {
+                return Err(DotParseError("strict graphs are not supported".into()))
+            }
+            Some(DotToken::Id(ref kw)) if kw == "graph" => {
+                return Err(DotParseError("only digraph is supported".into()))
+            }
+            other => return Err(DotParseError(format!("expected digraph, got {:?}", other))),
+        }
+        let mut token = tokens.next_token()?;
+        if let Some(DotToken::Id(_)) = token {
+            token = tokens.next_token()?;
+        }
+        if token != Some(DotToken::Symbol("{")) {
+            return Err(DotParseError(format!("expected '{{', got {:?}", token)));
+        }
+
+        let mut node_ids: Vec<String> = Vec::new();
+        let mut node_attrs: HashMap<String, HashMap<String, String>> = HashMap::new();
+        let mut edges: Vec<(String, String, HashMap<String, String>)> = Vec::new();
+        loop {
+            let id = match tokens.next_token()? {
+                Some(DotToken::Symbol("}")) => break,
+                Some(DotToken::Symbol(";")) => continue,
+                Some(DotToken::Id(id)) => id,
+                other => return Err(DotParseError(format!("unexpected {:?}", other))),
+            };
+            if id == "subgraph" {
+                return Err(DotParseError("subgraphs are not supported".into()));
+            }
+            let mut chain = vec![id];
+            let mut token = tokens.next_token()?;
+            while token == Some(DotToken::Symbol("->")) {
+                match tokens.next_token()? {
+                    Some(DotToken::Id(id)) => chain.push(id),
+                    other => return Err(DotParseError(format!("expected node, got {:?}", other))),
+                }
+                token = tokens.next_token()?;
+            }
+            let mut attrs = HashMap::new();
+            if token == Some(DotToken::Symbol("[")) {
+                attrs = tokens.attributes()?;
+                token = tokens.next_token()?;
+            }
+            match token {
+                Some(DotToken::Symbol(";")) => {}
+                Some(DotToken::Symbol("}")) => tokens.push_back(DotToken::Symbol("}")),
+                Some(DotToken::Id(id)) => tokens.push_back(DotToken::Id(id)),
+                other => return Err(DotParseError(format!("unexpected {:?}", other))),
+            }
+
+            if ["graph", "node", "edge"].contains(&chain[0].as_str()) && chain.len() == 1 {
+                continue; // default attributes are ignored
+            }
+            for id in &chain {
+                if !node_attrs.contains_key(id) {
+                    node_ids.push(id.clone());
+                    node_attrs.insert(id.clone(), HashMap::new());
+                }
+            }
+            if chain.len() == 1 {
+                node_attrs.get_mut(&chain[0]).unwrap().extend(attrs);
+            } else {
+                for pair in chain.windows(2) {
+                    edges.push((pair[0].clone(), pair[1].clone(), attrs.clone()));
+                }
+            }
+        }
+
+        let mut graph = Graph::default();
+        let mut ids: HashMap<&str, Nd> = HashMap::new();
+        for id in &node_ids {
+            let attrs = &node_attrs[id];
+            let label = attrs.get("label").unwrap_or(id).clone();
+            let is_category = attrs.get("category").is_none_or(|c| c != "false");
+            ids.insert(id, graph.find_or_add_vertex((label, is_category)));
+        }
+        for (l, r, attrs) in edges {
+            let label = attrs.get("label").cloned().unwrap_or_default();
+            graph.add_edge((ids[l.as_str()], ids[r.as_str()]), label);
+        }
+        Ok(graph)
+    }
+
+    /// Walk graph DFS post order.
+    ///
+    /// # Arguments
+    /// * `start` - start node
+    /// * `f` - visiting function
+    ///
+    /// # Return value
+    /// Bit vector representing visited nodes, edges that were cut to break loops,
+    /// `(discovery_time, finish_time)` of every node (`(0, 0)` for unvisited ones)
+    /// and type of every edge reachable from `start`.
+    pub fn walk_dfs_post_order<F>(&self, start: Nd, f: F) -> Result<DfsResult, Box<dyn Error>>
+    where
+        F: FnMut(Nd, &Vec<Nd>) -> Result<(), Box<dyn Error>>,
+    {
+        self.walk_post_order(start, false, f)
+    }
+
+    /// Nodes reachable from `start` in DFS pre order (parent before its children).
+    pub fn dfs_iter(&self, start: Nd) -> DfsIter<'_> {
+        DfsIter::new(self, start, false)
+    }
+
+    /// Nodes reachable from `start` in DFS post order (children before their parent,
+    /// unless they are on a loop).
+    pub fn dfs_post_order_iter(&self, start: Nd) -> DfsIter<'_> {
+        DfsIter::new(self, start, true)
+    }
+
+    /// Nodes reachable from `start` with their depth (shortest distance from `start`),
+    /// in BFS order.
+    pub fn bfs_iter(&self, start: Nd) -> BfsIter<'_> {
+        BfsIter::new(self, start, false)
+    }
+
+    /// Copy of outgoing adjacency lists in CSR layout, for faster repeated traversals.
+    pub fn to_csr(&self) -> CsrGraph {
+        CsrGraph::from_adjacency(self.node_data.iter().map(|d| d.outgoing.as_slice()))
+    }
+
+    /// Like `to_csr`, but with incoming edges.
+    fn to_reversed_csr(&self) -> CsrGraph {
+        CsrGraph::from_adjacency(self.node_data.iter().map(|d| d.incoming.as_slice()))
+    }
+
+    /// CSR copy of outgoing (or incoming, when `reversed`) edges, made once per generation.
+    fn cached_csr(&self, reversed: bool) -> Rc<CsrGraph> {
+        let mut cache = self.csr_cache.borrow_mut();
+        let slot = if reversed {
+            &mut cache.incoming
+        } else {
+            &mut cache.outgoing
+        };
+        match slot {
+            Some((generation, csr)) if *generation == self.generation => csr.clone(),
+            _ => {
+                let csr = Rc::new(if reversed {
+                    self.to_reversed_csr()
+                } else {
+                    self.to_csr()
+                });
+                *slot = Some((self.generation, csr.clone()));
+                csr
+            }
+        }
+    }
+
+    /// Walk graph DFS post order following edges backwards (from child to parent).
+    ///
+    /// # Arguments
+    /// * `start` - start node
+    /// * `f` - visiting function
+    ///
+    /// # Return value
+    /// Bit vector representing visited nodes.
+    pub fn walk_reversed_dfs_post_order<F>(&self, start: Nd, f: F) -> Result<BitVec, Box<dyn Error>>
+    where
+        F: FnMut(Nd, &Vec<Nd>) -> Result<(), Box<dyn Error>>,
+    {
+        self.walk_post_order(start, true, f)
+            .map(|(visited, _, _, _)| visited)
+    }
+
+    fn walk_post_order<F>(
+        &self,
+        start: Nd,
+        reversed: bool,
+        mut f: F,
+    ) -> Result<DfsResult, Box<dyn Error>>
+    where
+        F: FnMut(Nd, &Vec<Nd>) -> Result<(), Box<dyn Error>>,
+    {
+        let csr = self.cached_csr(reversed);
+        let children = |n: Nd| csr.outgoing_neighbors(n);
+        self.assert_valid_vertex(start);
+        let mut visited = BitVec::from_elem(self.node_data.len(), false);
+        let mut stack: Vec<(Nd, usize)> = Vec::new(); // (node, children_visited)
+        let mut path: HashSet<usize> = HashSet::new();
+        let mut edge_cuts: CollectingHashMap<usize, usize> = CollectingHashMap::new();
+        let mut cycles: Vec<CycleEvent> = Vec::new();
+        let mut times: Vec<(usize, usize)> = vec![(0, 0); self.node_data.len()];
+        let mut edge_types: HashMap<Ed, EdgeType> = HashMap::new();
+        let mut time = 0;
+        stack.push((start, 0));
+        while !stack.is_empty() {
+            let (node, children_visited) = stack.pop().unwrap();
+            path.insert(node);
+            tracing::trace!(node, children_visited, "pop");
+            visited.set(node, true);
+            if children_visited == 0 {
+                time += 1;
+                times[node].0 = time;
+            }
+            if children_visited < children(node).len() {
+                stack.push((node, children_visited + 1));
+                let next_child = children(node)[children_visited];
+                let edge_type = if path.contains(&next_child) {
+                    EdgeType::Back
+                } else if !visited.get(next_child).unwrap() {
+                    EdgeType::Tree
+                } else if times[node].0 < times[next_child].0 {
+                    EdgeType::Forward
+                } else {
+                    EdgeType::Cross
+                };
+                edge_types.insert((node, next_child), edge_type);
+                if edge_type == EdgeType::Back {
+                    tracing::debug!(from = node, to = next_child, "loop found");
+                    cycles.push(CycleEvent {
+                        from_label: self.get_vertex_label(node).0.clone(),
+                        to_label: self.get_vertex_label(next_child).0.clone(),
+                        from_id: node,
+                        to_id: next_child,
+                    });
+                    edge_cuts.insert(node, next_child);
+                }
+                if !visited.get(next_child).unwrap() {
+                    stack.push((next_child, 0));
+                }
+            } else {
+                // all children are visited, so call function (post order)
+                let empty: Vec<usize> = vec![];
+                let forbidden = edge_cuts.get_all(&node).unwrap_or(&empty);
+                f(node, forbidden)?;
+                path.remove(&node);
+                time += 1;
+                times[node].1 = time;
+            }
+        }
+
+        Ok((visited, cycles, times, edge_types))
+    }
+
+    /// Visit all nodes in (reversed) topological order using Kahn's algorithm.
+    ///
+    /// Every node is visited after all of its children, so a parent can use
+    /// results computed for its children.
+    ///
+    /// # Arguments
+    /// * `f` - visiting function
+    ///
+    /// # Return value
+    /// `CycleError` listing nodes that couldn't be visited if the graph has a loop.
+    /// All other nodes are visited before the error is returned.
+    pub fn visit_in_topological_order<F>(&self, f: F) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(Nd) -> Result<(), Box<dyn Error>>,
+    {
+        self.visit_nodes_in_topological_order(BitVec::from_elem(self.len(), true), f)
+    }
+
+    /// Visit nodes reachable from `start` in (reversed) topological order.
+    ///
+    /// Works like `visit_in_topological_order`, but other nodes are ignored,
+    /// so are loops among them.
+    pub fn visit_reachable_in_topological_order<F>(
+        &self,
+        start: Nd,
+        f: F,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(Nd) -> Result<(), Box<dyn Error>>,
+    {
+        self.assert_valid_vertex(start);
+        let mut reachable = BitVec::from_elem(self.len(), false);
+        for n in self.dfs_iter(start) {
+            reachable.set(n, true);
+        }
+        self.visit_nodes_in_topological_order(reachable, f)
+    }
+
+    /// Kahn's algorithm restricted to `nodes`, which must be closed under
+    /// taking children.
+    fn visit_nodes_in_topological_order<F>(
+        &self,
+        nodes: BitVec,
+        mut f: F,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(Nd) -> Result<(), Box<dyn Error>>,
+    {
+        let mut remaining: Vec<usize> = self.node_data.iter().map(|d| d.outgoing.len()).collect();
+        let mut queue: VecDeque<Nd> = (0..self.len())
+            .filter(|n| nodes[*n] && remaining[*n] == 0)
+            .collect();
+        while let Some(n) = queue.pop_front() {
+            f(n)?;
+            for parent in &self.node_data[n].incoming {
+                if !nodes[*parent] {
+                    continue;
+                }
+                remaining[*parent] -= 1;
+                if remaining[*parent] == 0 {
+                    queue.push_back(*parent);
+                }
+            }
+        }
+        let unvisited: Vec<Nd> = (0..self.len())
+            .filter(|n| nodes[*n] && remaining[*n] > 0)
+            .collect();
+        if unvisited.is_empty() {
+            Ok(())
+        } else {
+            Err(Box::new(CycleError { unvisited }))
+        }
+    }
+
+    /// Compute betweenness centrality of every node using Brandes' algorithm.
+    ///
+    /// # Return value
+    /// Vector parallel to `node_data` with centrality of each node.
+    pub fn betweenness_centrality(&self) -> Vec<f64> {
+        self.brandes(0..self.len(), 1.0)
+    }
+
+    /// Approximate betweenness centrality using only `k` random source nodes.
+    ///
+    /// Results are scaled by `len / k`, so they are comparable with
+    /// `betweenness_centrality`.
+    pub fn betweenness_centrality_sampled(&self, k: usize) -> Vec<f64> {
+        let n = self.len();
+        if k >= n {
+            return self.betweenness_centrality();
+        }
+        let sources = sample(&mut rand::thread_rng(), n, k);
+        self.brandes(sources.into_iter(), n as f64 / k as f64)
+    }
+
+    fn brandes<I>(&self, sources: I, scale: f64) -> Vec<f64>
+    where
+        I: Iterator<Item = Nd>,
+    {
+        let n = self.len();
+        let mut centrality = vec![0.0; n];
+        let mut sigma = vec![0.0f64; n];
+        let mut dist: Vec<Option<usize>> = vec![None; n];
+        let mut delta = vec![0.0f64; n];
+        let mut predecessors: Vec<Vec<Nd>> = vec![Vec::new(); n];
+        let mut order: Vec<Nd> = Vec::new();
+        let mut queue: VecDeque<Nd> = VecDeque::new();
+
+        for s in sources {
+            for v in 0..n {
+                sigma[v] = 0.0;
+                dist[v] = None;
+                delta[v] = 0.0;
+                predecessors[v].clear();
+            }
+            order.clear();
+            sigma[s] = 1.0;
+            dist[s] = Some(0);
+            queue.push_back(s);
+
+            // BFS phase: count shortest paths from `s`
+            while let Some(v) = queue.pop_front() {
+                order.push(v);
+                let d = dist[v].unwrap() + 1;
+                for &w in &self.node_data[v].outgoing {
+                    if dist[w].is_none() {
+                        dist[w] = Some(d);
+                        queue.push_back(w);
+                    }
+                    if dist[w] == Some(d) {
+                        sigma[w] += sigma[v];
+                        predecessors[w].push(v);
+                    }
+                }
+            }
+
+            // accumulation phase: back-propagate dependencies
+            while let Some(w) = order.pop() {
+                for &v in &predecessors[w] {
+                    delta[v] += sigma[v] / sigma[w] * (1.0 + delta[w]);
+                }
+                if w != s {
+                    centrality[w] += delta[w] * scale;
+                }
+            }
+        }
+
+        centrality
+    }
+
+    /// Compute PageRank of every node by power iteration.
+    ///
+    /// Ranks of nodes without outgoing edges are spread evenly over all nodes.
+    /// Iteration stops when L1 distance between consecutive rank vectors drops
+    /// below `epsilon` or after `max_iterations`.
+    ///
+    /// # Return value
+    /// Vector parallel to `node_data` with rank of each node, summing to 1.0.
+    pub fn pagerank(&self, damping: f64, epsilon: f64, max_iterations: usize) -> Vec<f64> {
+        let n = self.len();
+        if n == 0 {
+            return Vec::new();
+        }
+        let mut ranks = vec![1.0 / n as f64; n];
+        for _ in 0..max_iterations {
+            let dangling: f64 = self
+                .node_data
+                .iter()
+                .zip(&ranks)
+                .filter(|(data, _)| data.outgoing.is_empty())
+                .map(|(_, rank)| rank)
+                .sum();
+            let base = (1.0 - damping + damping * dangling) / n as f64;
+            let mut next = vec![base; n];
+            for (v, data) in self.node_data.iter().enumerate() {
+                let share = damping * ranks[v] / data.outgoing.len() as f64;
+                for &w in &data.outgoing {
+                    next[w] += share;
+                }
+            }
+            normalize_ranks(&mut next);
+            let delta = self.pagerank_delta(&ranks, &next);
+            ranks = next;
+            if delta < epsilon {
+                break;
+            }
+        }
+        ranks
+    }
+
+    /// L1 distance between two rank vectors.
+    fn pagerank_delta(&self, a: &[f64], b: &[f64]) -> f64 {
+        a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum()
+    }
+
+    /// Count distinct paths from `from` to `to`.
+    ///
+    /// Edges closing a loop are not followed, so for graphs with cycles
+    /// the result is an approximation.
+    pub fn path_count(&self, from: Nd, to: Nd) -> usize {
+        let mut counts: Vec<Option<usize>> = vec![None; self.len()];
+        let mut on_path = BitVec::from_elem(self.len(), false);
+        let mut stack: Vec<(Nd, usize)> = vec![(from, 0)]; // (node, children_visited)
+        on_path.set(from, true);
+        while let Some((node, children_visited)) = stack.pop() {
+            let outgoing = &self.node_data[node].outgoing;
+            if node == to {
+                counts[node] = Some(1);
+                on_path.set(node, false);
+            } else if children_visited < outgoing.len() {
+                stack.push((node, children_visited + 1));
+                let next_child = outgoing[children_visited];
+                if counts[next_child].is_none() && !on_path.get(next_child).unwrap() {
+                    on_path.set(next_child, true);
+                    stack.push((next_child, 0));
+                }
+            } else {
+                let total = outgoing
+                    .iter()
+                    .filter_map(|c| counts[*c])
+                    .fold(0, usize::saturating_add);
+                counts[node] = Some(total);
+                on_path.set(node, false);
+            }
+        }
+        counts[from].unwrap_or(0)
+    }
+
+    /// List simple paths from `from` to `to`, returning at most `max_paths` of them.
+    pub fn all_paths(&self, from: Nd, to: Nd, max_paths: usize) -> Vec<Vec<Nd>> {
+        let mut result = Vec::new();
+        let mut on_path = BitVec::from_elem(self.len(), false);
+        let mut path: Vec<(Nd, usize)> = vec![(from, 0)]; // (node, children_visited)
+        on_path.set(from, true);
+        while result.len() < max_paths {
+            let (node, children_visited) = match path.last_mut() {
+                Some(top) => top,
+                None => break,
+            };
+            let node = *node;
+            if node == to {
+                result.push(path.iter().map(|(n, _)| *n).collect());
+            } else if let Some(next_child) = self.node_data[node].outgoing.get(*children_visited) {
+                *children_visited += 1;
+                if !on_path.get(*next_child).unwrap() {
+                    on_path.set(*next_child, true);
+                    path.push((*next_child, 0));
+                }
+                continue;
+            }
+            on_path.set(node, false);
+            path.pop();
+        }
+        result
+    }
+
+    /// List simple paths from `from` to `to` having at most `max_length` edges.
+    ///
+    /// # Return value
+    /// At most `MAX_ENUMERATED_PATHS` paths and a flag telling if there were more
+    /// (and the list was truncated).
+    pub fn enumerate_simple_paths(
+        &self,
+        from: Nd,
+        to: Nd,
+        max_length: usize,
+    ) -> (Vec<Vec<Nd>>, bool) {
+        let mut result = Vec::new();
+        let mut on_path = BitVec::from_elem(self.len(), false);
+        let mut path: Vec<(Nd, usize)> = vec![(from, 0)]; // (node, children_visited)
+        on_path.set(from, true);
+        loop {
+            let length = path.len();
+            let (node, children_visited) = match path.last_mut() {
+                Some(top) => top,
+                None => break,
+            };
+            let node = *node;
+            if node == to {
+                if result.len() == MAX_ENUMERATED_PATHS {
+                    return (result, true);
+                }
+                result.push(path.iter().map(|(n, _)| *n).collect());
+            } else if length <= max_length {
+                if let Some(next_child) = self.node_data[node].outgoing.get(*children_visited) {
+                    *children_visited += 1;
+                    if !on_path.get(*next_child).unwrap() {
+                        on_path.set(*next_child, true);
+                        path.push((*next_child, 0));
+                    }
+                    continue;
+                }
+            }
+            on_path.set(node, false);
+            path.pop();
+        }
+        (result, false)
+    }
+
+    /// Find nodes whose shortest distance from `root` is exactly `depth` hops.
+    pub fn nodes_at_depth(&self, root: Nd, depth: usize) -> Vec<Nd> {
+        let mut visited = BitVec::from_elem(self.len(), false);
+        let mut level = vec![root];
+        visited.set(root, true);
+        for _ in 0..depth {
+            let mut next_level = Vec::new();
+            for n in level {
+                for child in &self.node_data[n].outgoing {
+                    if !visited.get(*child).unwrap() {
+                        visited.set(*child, true);
+                        next_level.push(*child);
+                    }
+                }
+            }
+            level = next_level;
+        }
+        level
+    }
+
+    /// Find the most specific common ancestor of `a` and `b`.
+    ///
+    /// Among nodes reachable from `root` that are ancestors of both `a` and `b`
+    /// (a node is its own ancestor), returns the one deepest below `root`.
+    pub fn lowest_common_ancestor(&self, a: Nd, b: Nd, root: Nd) -> Option<Nd> {
+        let depth = self.bfs_distances(root, false, usize::MAX);
+        let from_a = self.ancestors_within_depth(a, usize::MAX);
+        let from_b = self.ancestors_within_depth(b, usize::MAX);
+        from_a
+            .keys()
+            .filter(|n| from_b.contains_key(n))
+            .filter_map(|n| depth[*n].map(|d| (d, *n)))
+            .max_by(|(d1, n1), (d2, n2)| d1.cmp(d2).then(n2.cmp(n1)))
+            .map(|(_, n)| n)
+    }
+
+    /// Find ancestors of a node that are at most `max_hops` edges away.
+    ///
+    /// # Return value
+    /// Map from ancestor to its shortest distance to `node`.
+    /// The node itself is included with distance 0.
+    pub fn ancestors_within_depth(&self, node: Nd, max_hops: usize) -> HashMap<Nd, usize> {
+        self.bfs_distances(node, true, max_hops)
+            .into_iter()
+            .enumerate()
+            .filter_map(|(n, d)| d.map(|d| (n, d)))
+            .collect()
+    }
+
+    /// Shortest distances from `start` following outgoing (or incoming, when
+    /// `reversed`) edges, up to `max_hops` steps. Unreachable nodes get `None`.
+    fn bfs_distances(&self, start: Nd, reversed: bool, max_hops: usize) -> Vec<Option<usize>> {
+        let mut distances = vec![None; self.len()];
+        // depths never decrease, so the walk can stop at the first node too far away
+        for (n, d) in BfsIter::new(self, start, reversed).take_while(|(_, d)| *d <= max_hops) {
+            distances[n] = Some(d);
+        }
+        distances
+    }
+
+    /// Find weakly connected components (edge direction is ignored).
+    ///
+    /// # Return value
+    /// Components as sorted vectors of nodes, biggest component first.
+    pub fn weakly_connected_components(&self) -> Vec<Vec<Nd>> {
+        let mut parent: Vec<Nd> = (0..self.len()).collect();
+
+        fn find(parent: &mut [Nd], mut n: Nd) -> Nd {
+            while parent[n] != n {
+                parent[n] = parent[parent[n]];
+                n = parent[n];
+            }
+            n
+        }
+
+        for (l, data) in self.node_data.iter().enumerate() {
+            for r in &data.outgoing {
+                let a = find(&mut parent, l);
+                let b = find(&mut parent, *r);
+                if a != b {
+                    parent[a.max(b)] = a.min(b);
+                }
+            }
+        }
+
+        let mut components: CollectingHashMap<Nd, Nd> = CollectingHashMap::new();
+        for n in 0..self.len() {
+            let root = find(&mut parent, n);
+            components.insert(root, n);
+        }
+
+        let mut result: Vec<Vec<Nd>> = components.drain().map(|(_, c)| c).collect();
+        for c in &mut result {
+            c.sort_unstable();
+        }
+        result.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+        result
+    }
+
+    /// `true` if all nodes are connected when edge direction is ignored.
+    pub fn is_weakly_connected(&self) -> bool {
+        self.weakly_connected_components().len() <= 1
+    }
+
+    /// `true` if every node is reachable from every other node.
+    pub fn is_strongly_connected(&self) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+        self.bfs_distances(0, false, usize::MAX)
+            .iter()
+            .chain(self.bfs_distances(0, true, usize::MAX).iter())
+            .all(|d| d.is_some())
+    }
+
+    /// Check if the graph (with edge direction ignored) is 2-colorable.
+    ///
+    /// # Return value
+    /// `Some((left, right))` with both parts of the graph sorted, or `None` if
+    /// some edge connects nodes of the same color.
+    pub fn is_bipartite(&self) -> Option<(Vec<Nd>, Vec<Nd>)> {
+        let mut color: Vec<Option<bool>> = vec![None; self.len()];
+        let mut queue: VecDeque<Nd> = VecDeque::new();
+        for start in 0..self.len() {
+            if color[start].is_some() {
+                continue;
+            }
+            color[start] = Some(false);
+            queue.push_back(start);
+            while let Some(n) = queue.pop_front() {
+                let c = color[n].unwrap();
+                let data = &self.node_data[n];
+                for m in data.outgoing.iter().chain(data.incoming.iter()) {
+                    match color[*m] {
+                        None => {
+                            color[*m] = Some(!c);
+                            queue.push_back(*m);
+                        }
+                        Some(other) if other == c => return None,
+                        _ => {}
+                    }
+                }
+            }
+        }
+        Some((0..self.len()).partition(|n| color[*n] == Some(false)))
+    }
+
+    /// Find strongly connected components using Tarjan's algorithm.
+    ///
+    /// # Return value
+    /// Components as sorted vectors of nodes, biggest component first.
+    pub fn strongly_connected_components(&self) -> Vec<Vec<Nd>> {
+        let n = self.len();
+        let mut index = vec![usize::MAX; n];
+        let mut lowlink = vec![0; n];
+        let mut on_stack = BitVec::from_elem(n, false);
+        let mut stack: Vec<Nd> = Vec::new();
+        let mut next_index = 0;
+        let mut result: Vec<Vec<Nd>> = Vec::new();
+        for start in 0..n {
+            if index[start] != usize::MAX {
+                continue;
+            }
+            let mut work: Vec<(Nd, usize)> = vec![(start, 0)]; // (node, children_visited)
+            while let Some((v, i)) = work.pop() {
+                if i == 0 {
+                    index[v] = next_index;
+                    lowlink[v] = next_index;
+                    next_index += 1;
+                    stack.push(v);
+                    on_stack.set(v, true);
+                }
+                let outgoing = &self.node_data[v].outgoing;
+                if i < outgoing.len() {
+                    let w = outgoing[i];
+                    work.push((v, i + 1));
+                    if index[w] == usize::MAX {
+                        work.push((w, 0));
+                    } else if on_stack.get(w).unwrap() {
+                        lowlink[v] = lowlink[v].min(index[w]);
+                    }
+                } else {
+                    if lowlink[v] == index[v] {
+                        let mut component = Vec::new();
+                        loop {
+                            let w = stack.pop().unwrap();
+                            on_stack.set(w, false);
+                            component.push(w);
+                            if w == v {
+                                break;
+                            }
+                        }
+                        component.sort_unstable();
+                        result.push(component);
+                    }
+                    if let Some((parent, _)) = work.last() {
+                        lowlink[*parent] = lowlink[*parent].min(lowlink[v]);
+                    }
+                }
+            }
+        }
+        result.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+        result
+    }
+
+    /// Compute summary metrics of the graph. Depths are shortest distances from `root`.
+    pub fn statistics(&self, root: Nd) -> GraphStatistics {
+        self.assert_valid_vertex(root);
+        let depths: Vec<usize> = self
+            .bfs_distances(root, false, usize::MAX)
+            .into_iter()
+            .flatten()
+            .collect();
+        let category_count = self.node_labels.iter().filter(|(_, l)| l.1).count();
+        let cycle_count = self
+            .strongly_connected_components()
+            .iter()
+            .filter(|c| c.len() > 1 || self.edge_labels.contains_key(&(c[0], c[0])))
+            .count();
+        let out_degree = |n: Nd| self.node_data[n].outgoing.len();
+        let mut by_degree: Vec<Nd> = (0..self.len()).collect();
+        by_degree.sort_by(|a, b| out_degree(*b).cmp(&out_degree(*a)).then(a.cmp(b)));
+        GraphStatistics {
+            node_count: self.len(),
+            edge_count: self.edge_count,
+            root_label: self.get_vertex_label(root).0.clone(),
+            max_depth: depths.iter().copied().max().unwrap_or(0),
+            average_depth: depths.iter().sum::<usize>() as f64 / depths.len() as f64,
+            category_count,
+            article_count: self.len() - category_count,
+            cycle_count,
+            top_out_degree: by_degree
+                .into_iter()
+                .take(10)
+                .map(|n| (self.get_vertex_label(n).0.clone(), out_degree(n)))
+                .collect(),
+        }
+    }
 
-impl Graph {
-    pub fn len(&self) -> usize {
-        self.node_data.len()
+    /// Write `statistics` of the graph as JSON.
+    pub fn export_statistics_json<W: Write>(&self, root: Nd, w: &mut W) -> serde_json::Result<()> {
+        serde_json::to_writer_pretty(w, &self.statistics(root))
     }
 
-    pub fn add_vertex(&mut self, label: (String, bool)) -> Nd {
-        let new_idx = self.node_data.len();
-        self.node_data.push(NodeData::default());
-        self.node_labels.insert(new_idx, label);
-        new_idx
+    /// Greedily find a big clique (edge direction is ignored).
+    ///
+    /// Starts with the node of highest degree and keeps adding the node
+    /// connected to all clique members that has the highest degree.
+    ///
+    /// # Arguments
+    /// * `time_limit_ms` - stop searching after this many milliseconds
+    ///
+    /// # Return value
+    /// Nodes of the clique, in order of adding.
+    pub fn approximate_max_clique(&self, time_limit_ms: u64) -> Vec<Nd> {
+        let deadline = Instant::now() + Duration::from_millis(time_limit_ms);
+        let degree = |n: Nd| self.node_data[n].outgoing.len() + self.node_data[n].incoming.len();
+        let neighbours = |n: Nd| -> HashSet<Nd> {
+            let data = &self.node_data[n];
+            data.outgoing
+                .iter()
+                .chain(data.incoming.iter())
+                .copied()
+                .filter(|m| *m != n)
+                .collect()
+        };
+        let by_degree = |a: &Nd, b: &Nd| degree(*a).cmp(&degree(*b)).then(b.cmp(a));
+        let start = match (0..self.len()).max_by(by_degree) {
+            Some(start) => start,
+            None => return Vec::new(),
+        };
+        let mut clique = vec![start];
+        let mut candidates = neighbours(start);
+        while Instant::now() < deadline {
+            match candidates.iter().copied().max_by(by_degree) {
+                Some(n) => {
+                    clique.push(n);
+                    let adjacent = neighbours(n);
+                    candidates.retain(|c| adjacent.contains(c));
+                }
+                None => break,
+            }
+        }
+        clique
     }
 
-    pub fn add_edge(&mut self, e: Ed, label: String) {
-        let (l, r) = e;
-        if l < self.node_labels.len() && r < self.node_labels.len() {
-            self.node_data[l].outgoing.push(r);
-            self.node_data[r].incoming.push(l);
-            self.edge_labels.insert(e, label);
+    /// Jaccard index of outgoing neighbour sets of `a` and `b`.
+    ///
+    /// # Return value
+    /// Value between `0.0` (nothing in common) and `1.0` (same children).
+    /// `0.0` if both nodes have no children.
+    pub fn jaccard_similarity(&self, a: Nd, b: Nd) -> f64 {
+        let na: HashSet<Nd> = self.node_data[a].outgoing.iter().copied().collect();
+        let nb: HashSet<Nd> = self.node_data[b].outgoing.iter().copied().collect();
+        let union = na.union(&nb).count();
+        if union == 0 {
+            return 0.0;
         }
+        na.intersection(&nb).count() as f64 / union as f64
     }
 
-    pub fn add(&mut self, vtx1: (String, bool), edge: String, vtx2: (String, bool)) {
-        let v1 = self.find_or_add_vertex(vtx1);
-        let v2 = self.find_or_add_vertex(vtx2);
-        self.add_edge((v1, v2), edge);
+    /// Random walk following outgoing edges.
+    ///
+    /// At every step a uniformly random child is chosen. Walk continues from
+    /// `start` when a node without children is reached.
+    ///
+    /// # Arguments
+    /// * `start` - start node
+    /// * `steps` - number of steps
+    /// * `seed` - seed of random number generator, same seed gives same walk
+    ///
+    /// # Return value
+    /// Visited nodes, beginning with `start` (`steps + 1` elements).
+    pub fn random_walk(&self, start: Nd, steps: usize, seed: u64) -> Vec<Nd> {
+        self.assert_valid_vertex(start);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut walk = Vec::with_capacity(steps + 1);
+        let mut current = start;
+        walk.push(current);
+        for _ in 0..steps {
+            current = *self.node_data[current]
+                .outgoing
+                .choose(&mut rng)
+                .unwrap_or(&start);
+            walk.push(current);
+        }
+        walk
     }
 
-    pub fn find_vertex(&self, label: &(String, bool)) -> Option<Nd> {
-        self.node_labels.get_by_right(&label).map(|x| *x)
+    /// One line summary of the graph, e.g.
+    /// `Graph(V=1024, E=3892, roots=3, leaves=512, max_depth=7)`.
+    ///
+    /// Depth of a node is its shortest distance from any root.
+    pub fn summarize(&self) -> String {
+        let mut depth: Vec<Option<usize>> = vec![None; self.len()];
+        let mut queue = VecDeque::new();
+        let mut leaves = 0;
+        for (n, data) in self.node_data.iter().enumerate() {
+            if data.incoming.is_empty() {
+                depth[n] = Some(0);
+                queue.push_back(n);
+            }
+            if data.outgoing.is_empty() {
+                leaves += 1;
+            }
+        }
+        let roots = queue.len();
+        let mut max_depth = 0;
+        while let Some(n) = queue.pop_front() {
+            let d = depth[n].unwrap();
+            max_depth = max_depth.max(d);
+            for next in &self.node_data[n].outgoing {
+                if depth[*next].is_none() {
+                    depth[*next] = Some(d + 1);
+                    queue.push_back(*next);
+                }
+            }
+        }
+        format!(
+            "Graph(V={}, E={}, roots={}, leaves={}, max_depth={})",
+            self.len(),
+            self.edge_count,
+            roots,
+            leaves,
+            max_depth
+        )
     }
+}
 
-    pub fn find_or_add_vertex(&mut self, label: (String, bool)) -> Nd {
-        if let Some(n) = self.find_vertex(&label) {
-            n
-        } else {
-            self.add_vertex(label)
+impl Display for Graph {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.summarize())
+    }
+}
+
+/// Levenshtein distance between `a` and `b` (Wagner-Fischer, two rows).
+fn edit_distance(a: &[char], b: &str) -> usize {
+    let mut previous: Vec<usize> = (0..=a.len()).collect();
+    let mut current = vec![0; a.len() + 1];
+    for (j, cb) in b.chars().enumerate() {
+        current[0] = j + 1;
+        for (i, ca) in a.iter().enumerate() {
+            let substitution = previous[i] + if *ca == cb { 0 } else { 1 };
+            current[i + 1] = substitution.min(previous[i + 1] + 1).min(current[i] + 1);
         }
+        std::mem::swap(&mut previous, &mut current);
     }
+    previous[a.len()]
+}
 
-    pub fn roots(&self) -> Vec<Nd> {
-        let mut result = Vec::new();
-        for (i, n) in self.node_data.iter().enumerate() {
-            if n.incoming.is_empty() {
-                result.push(i);
+/// Divide ranks by their sum, so that they sum to 1.0 (unless all are zero).
+fn normalize_ranks(ranks: &mut [f64]) {
+    let sum: f64 = ranks.iter().sum();
+    if sum > 0.0 {
+        for rank in ranks.iter_mut() {
+            *rank /= sum;
+        }
+    }
+}
+
+/// Split CSV line into fields. `None` if a quote isn't closed.
+fn split_csv_record(line: &str) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
             }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
         }
-        result
     }
+    if quoted {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
 
-    pub fn get_vertex_label(&self, id: Nd) -> &(String, bool) {
-        self.node_labels.get_by_left(&id).unwrap()
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[derive(Debug, PartialEq)]
+enum DotToken {
+    Id(String),
+    Symbol(&'static str),
+}
+
+/// Tokenizer for `Graph::from_dot`.
+struct DotTokens<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    pushed_back: Option<DotToken>,
+}
+
+impl<'a> DotTokens<'a> {
+    fn new(source: &'a str) -> DotTokens<'a> {
+        DotTokens {
+            chars: source.chars().peekable(),
+            pushed_back: None,
+        }
     }
 
-    pub fn get_edge_label(&self, e: &Ed) -> &str {
-        self.edge_labels.get(e).unwrap()
+    fn push_back(&mut self, token: DotToken) {
+        self.pushed_back = Some(token);
     }
 
-    /// Walk graph DFS post order.
-    ///
-    /// # Arguments
-    /// * `start` - start node
-    /// * `f` - visiting function
-    ///
-    /// # Return value
-    /// Bit vector representing visited nodes.
-    pub fn walk_dfs_post_order<F>(&self, start: Nd, mut f: F) -> Result<BitVec, Box<dyn Error>>
-    where
-        F: FnMut(Nd, &Vec<Nd>) -> Result<(), Box<dyn Error>>,
-    {
-        let mut visited = BitVec::from_elem(self.node_data.len(), false);
-        let mut stack: Vec<(Nd, usize)> = Vec::new(); // (node, children_visited)
-        let mut path: HashSet<usize> = HashSet::new();
-        let mut edge_cuts: CollectingHashMap<usize, usize> = CollectingHashMap::new();
-        stack.push((start, 0));
-        while !stack.is_empty() {
-            let (node, children_visited) = stack.pop().unwrap();
-            path.insert(node);
-            // println!("pop {}", node);
-            visited.set(node, true);
-            if children_visited < self.node_data[node].outgoing.len() {
-                stack.push((node, children_visited + 1));
-                let next_child = self.node_data[node].outgoing[children_visited];
-                if path.contains(&next_child) {
-                    let node_label = self.get_vertex_label(node);
-                    let child_label = self.get_vertex_label(next_child);
-                    println!(
-                        "Found loop between '{}' ({}) and '{}' ({})",
-                        node_label.0, node, child_label.0, next_child
-                    );
-                    edge_cuts.insert(node, next_child);
+    fn next_token(&mut self) -> Result<Option<DotToken>, DotParseError> {
+        if let Some(token) = self.pushed_back.take() {
+            return Ok(Some(token));
+        }
+        self.skip_whitespace_and_comments();
+        let c = match self.chars.next() {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+        let token = match c {
+            '{' => DotToken::Symbol("{"),
+            '}' => DotToken::Symbol("}"),
+            '[' => DotToken::Symbol("["),
+            ']' => DotToken::Symbol("]"),
+            '=' => DotToken::Symbol("="),
+            ';' => DotToken::Symbol(";"),
+            ',' => DotToken::Symbol(","),
+            '-' if self.chars.peek() == Some(&'>') => {
+                self.chars.next();
+                DotToken::Symbol("->")
+            }
+            '-' if self.chars.peek() == Some(&'-') => {
+                return Err(DotParseError("undirected edges are not supported".into()))
+            }
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match self.chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match self.chars.next() {
+                            Some('"') => value.push('"'),
+                            Some(c) => {
+                                value.push('\\');
+                                value.push(c);
+                            }
+                            None => break,
+                        },
+                        Some(c) => value.push(c),
+                        None => return Err(DotParseError("unterminated string".into())),
+                    }
                 }
-                if !visited.get(next_child).unwrap() {
-                    stack.push((next_child, 0));
+                DotToken::Id(value)
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' => {
+                let mut value = c.to_string();
+                while let Some(&c) = self.chars.peek() {
+                    if c.is_alphanumeric() || c == '_' || c == '.' {
+                        value.push(c);
+                        self.chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                DotToken::Id(value)
+            }
+            c => return Err(DotParseError(format!("unexpected character '{}'", c))),
+        };
+        Ok(Some(token))
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() {
+                self.chars.next();
+            } else if c == '#' {
+                self.skip_line();
+            } else if c == '/' {
+                self.chars.next();
+                match self.chars.next() {
+                    Some('/') => self.skip_line(),
+                    Some('*') => {
+                        let mut prev = ' ';
+                        for c in &mut self.chars {
+                            if prev == '*' && c == '/' {
+                                break;
+                            }
+                            prev = c;
+                        }
+                    }
+                    _ => {}
                 }
             } else {
-                // all children are visited, so call function (post order)
-                let empty: Vec<usize> = vec![];
-                let forbidden = edge_cuts.get_all(&node).unwrap_or(&empty);
-                f(node, forbidden)?;
-                path.remove(&node);
+                break;
+            }
+        }
+    }
+
+    fn skip_line(&mut self) {
+        for c in &mut self.chars {
+            if c == '\n' {
+                break;
             }
         }
+    }
 
-        Ok(visited)
+    /// Read attribute list after opening `[`, including closing `]`.
+    fn attributes(&mut self) -> Result<HashMap<String, String>, DotParseError> {
+        let mut attrs = HashMap::new();
+        loop {
+            let key = match self.next_token()? {
+                Some(DotToken::Symbol("]")) => return Ok(attrs),
+                Some(DotToken::Symbol(",")) | Some(DotToken::Symbol(";")) => continue,
+                Some(DotToken::Id(key)) => key,
+                other => return Err(DotParseError(format!("unexpected {:?}", other))),
+            };
+            if self.next_token()? != Some(DotToken::Symbol("=")) {
+                return Err(DotParseError(format!("expected '=' after '{}'", key)));
+            }
+            match self.next_token()? {
+                Some(DotToken::Id(value)) => attrs.insert(key, value),
+                other => return Err(DotParseError(format!("unexpected {:?}", other))),
+            };
+        }
     }
 }
 
+/// Node attribute holding number of articles in a category.
+pub const ARTICLE_COUNT: &str = "article_count";
+
+/// Value of `ARTICLE_COUNT` attribute of node `n`.
+pub fn article_count(graph: &Graph, n: Nd) -> usize {
+    graph
+        .get_node_attribute(n, ARTICLE_COUNT)
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Node attribute holding number of cites extracted from an article.
+pub const CITE_COUNT: &str = "cite_count";
+
+/// Value of `CITE_COUNT` attribute of node `n`.
+pub fn cite_count(graph: &Graph, n: Nd) -> usize {
+    graph
+        .get_node_attribute(n, CITE_COUNT)
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Node attribute holding timestamp of the last revision of a page (as in the dump).
+pub const LAST_MODIFIED: &str = "last_modified";
+
+/// Node attributes written to GEXF, with their types.
+const GEXF_ATTRIBUTES: &[(&str, &str)] = &[
+    (ARTICLE_COUNT, "integer"),
+    (CITE_COUNT, "integer"),
+    (LAST_MODIFIED, "string"),
+];
+
 #[derive(Default, Debug)]
 pub struct CategoryExtractor {
     site: String,
     is_category: bool,
     pub graph: Graph,
     pub normalizer: Normalizer,
+    pub new_edges: usize,
+    pub repeated_edges: usize,
+    /// Position of the ordered list item being extracted (category pages only).
+    list_order: Option<u32>,
+    /// Text of the page being extracted, see `set_source`.
+    source: String,
+    /// Sort key given in category link, for every edge created from one
+    /// (empty for `[[Category:X|]]`, missing for `[[Category:X]]`).
+    pub ordinals: HashMap<Ed, String>,
 }
 
 impl CategoryExtractor {
-    pub fn set_site(&mut self, site: String) {
-        self.site = site
+    /// Set name of the page being processed (string is copied only when it changes).
+    pub fn set_site(&mut self, site: impl AsRef<str> + Into<String>) {
+        if self.site != site.as_ref() {
+            self.site = site.into();
+        }
     }
 
     pub fn set_is_category(&mut self, is_category: bool) {
         self.is_category = is_category;
     }
 
+    /// Set text of the page which is going to be extracted.
+    ///
+    /// The parser gives the same node for `[[Category:X|]]` and `[[Category:X]]`,
+    /// so source of the link is needed to tell empty sort key from missing one.
+    /// Without it empty sort keys are not stored in `ordinals`.
+    pub fn set_source(&mut self, text: &str) {
+        self.source.clear();
+        self.source.push_str(text);
+    }
+
+    /// Number of articles (non-category pages) linking to category `n`.
+    ///
+    /// It's kept in `"article_count"` attribute of graph nodes.
+    pub fn article_count_for(&self, n: Nd) -> usize {
+        article_count(&self.graph, n)
+    }
+
+    fn increment_article_count(&mut self, n: Nd) {
+        let count = self.article_count_for(n) + 1;
+        self.graph
+            .set_node_attribute(n, ARTICLE_COUNT, count.to_string());
+    }
+
+    /// Use already built graph (e.g. imported from CSV) instead of extracting one.
+    ///
+    /// Articles are counted from edges leading from categories to non-category nodes.
+    pub fn set_graph(&mut self, graph: Graph) {
+        self.new_edges = graph.edge_count();
+        self.repeated_edges = 0;
+        self.graph = graph;
+        for n in 0..self.graph.len() {
+            if !self.graph.get_vertex_label(n).1 {
+                for parent in self.graph.direct_parents(n).to_vec() {
+                    self.increment_article_count(parent);
+                }
+            }
+        }
+    }
+
+    /// Remove categories without articles and without subcategories.
+    ///
+    /// Repeats until no more categories become empty.
+    ///
+    /// # Return value
+    /// Number of removed nodes.
+    pub fn prune_empty_categories(&mut self) -> usize {
+        let mut total = 0;
+        loop {
+            let graph = &self.graph;
+            let empty: HashSet<Nd> = graph
+                .node_labels_iter()
+                .filter(|(n, (_, is_category))| {
+                    *is_category
+                        && graph.direct_children(*n).is_empty()
+                        && article_count(graph, *n) == 0
+                })
+                .map(|(n, _)| n)
+                .collect();
+            if empty.is_empty() {
+                return total;
+            }
+            total += empty.len();
+            let mapping = self.graph.retain_vertices(|n| !empty.contains(&n));
+            self.ordinals = self
+                .ordinals
+                .drain()
+                .filter_map(|((l, r), ordinal)| Some(((mapping[l]?, mapping[r]?), ordinal)))
+                .collect();
+        }
+    }
+
     pub fn extract(&mut self, parsed: &Output) {
         for n in &parsed.nodes {
             self.extract_node(n)
@@ -157,23 +2366,63 @@ impl CategoryExtractor {
     pub fn extract_node(&mut self, node: &Node) {
         match node {
             Node::Category {
-                target, ordinal, ..
+                target,
+                ordinal,
+                start,
+                end,
             } => {
-                let target = self.normalizer.normalize_category_name(*target);
-                if !target.1 {
-                    panic!("Category target '{}' is not a category!", target.0);
+                let (name, is_category, original) = self
+                    .normalizer
+                    .normalize_category_name_with_original(target);
+                if !is_category {
+                    // bad markup in a page shouldn't stop processing of the whole dump
+                    tracing::warn!(
+                        site = %self.site,
+                        "Category target '{}' (normalized to '{}') is not a category, skipping link",
+                        original,
+                        name
+                    );
+                    return;
                 }
-                // println!("TARGET: {}", target_name);
+                let target = (name, is_category);
+                tracing::debug!(target = %target.0, site = %self.site, "category link");
                 let mut extr = TextExtractor::new();
                 extr.extract_nodes_text(ordinal);
-                let mut label = extr.result().trim().to_string();
+                // target can't contain `|`, so it's there only before sort key
+                let has_ordinal = !ordinal.is_empty()
+                    || self
+                        .source
+                        .get(*start..*end)
+                        .is_some_and(|link| link.contains('|'));
+                let ordinal = extr.result().trim().to_string();
+                let mut label = ordinal.clone();
                 if label.is_empty()
                     || label.len() == 1 && !label.chars().next().unwrap().is_alphanumeric()
                 {
                     label = self.site.clone();
                 }
-                self.graph
-                    .add(target, label, (self.site.clone(), self.is_category))
+                let target_key = target.clone();
+                let site_key = (self.site.clone(), self.is_category);
+                let (_, edge_created, _) = self.graph.add(target, label, site_key.clone());
+                let e = (
+                    self.graph.find_vertex(&target_key).unwrap(),
+                    self.graph.find_vertex(&site_key).unwrap(),
+                );
+                if has_ordinal {
+                    self.ordinals.entry(e).or_insert(ordinal);
+                }
+                if let Some(order) = self.list_order {
+                    self.graph.set_edge_order(e, order);
+                }
+                if edge_created {
+                    self.new_edges += 1;
+                    if !self.is_category {
+                        let n = self.graph.find_vertex(&target_key).unwrap();
+                        self.increment_article_count(n);
+                    }
+                } else {
+                    self.repeated_edges += 1;
+                }
             }
             Node::DefinitionList { items, .. } => {
                 for item in items {
@@ -187,6 +2436,14 @@ impl CategoryExtractor {
             Node::Link { text, .. } => {
                 self.extract_nodes(text);
             }
+            Node::OrderedList { items, .. } if self.is_category => {
+                let outer = self.list_order;
+                for (i, item) in items.iter().enumerate() {
+                    self.list_order = Some(i as u32 + 1);
+                    self.extract_list_item(item);
+                }
+                self.list_order = outer;
+            }
             Node::OrderedList { items, .. } => {
                 for item in items {
                     self.extract_list_item(item);
@@ -237,6 +2494,7 @@ impl CategoryExtractor {
     }
 }
 
+#[derive(Clone)]
 pub struct Normalizer {
     kat_match: Regex,
     space_match: Regex,
@@ -245,9 +2503,17 @@ pub struct Normalizer {
 
 impl Default for Normalizer {
     fn default() -> Self {
+        Self::with_category_prefixes(&["Kategoria", "Category"])
+    }
+}
+
+impl Normalizer {
+    /// Create normalizer recognizing given category namespace names (case insensitive).
+    pub fn with_category_prefixes(prefixes: &[&str]) -> Normalizer {
         let left_to_right = "\u{200E}";
+        let prefixes: Vec<String> = prefixes.iter().map(|p| regex::escape(p)).collect();
         Self {
-            kat_match: RegexBuilder::new(r"^(Kategoria|Category):")
+            kat_match: RegexBuilder::new(&format!("^({}):", prefixes.join("|")))
                 .case_insensitive(true)
                 .build()
                 .unwrap(),
@@ -257,9 +2523,7 @@ impl Default for Normalizer {
             bad_chars: vec![left_to_right],
         }
     }
-}
 
-impl Normalizer {
     pub fn normalize_category_name(&self, s: &str) -> (String, bool) {
         let mut s = s;
         let is_category;
@@ -278,6 +2542,12 @@ impl Normalizer {
         }
         (s, is_category)
     }
+
+    /// Like `normalize_category_name`, but also returns the original string (for error messages).
+    pub fn normalize_category_name_with_original<'a>(&self, s: &'a str) -> (String, bool, &'a str) {
+        let (normalized, is_category) = self.normalize_category_name(s);
+        (normalized, is_category, s)
+    }
 }
 
 // Don't display it
@@ -286,3 +2556,447 @@ impl Debug for Normalizer {
         Result::Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    fn graph_with_edges(labels: &[&str], edges: &[(Nd, Nd)]) -> Graph {
+        let mut graph = Graph::default();
+        for label in labels {
+            graph.add_vertex((label.to_string(), true));
+        }
+        for e in edges {
+            graph.add_edge(*e, String::new());
+        }
+        graph
+    }
+
+    #[test]
+    fn walk_dfs_post_order_visits_all_nodes_of_graph_with_cycle() {
+        let graph = Graph::from_dot("digraph { A -> B -> C -> A; D -> A }").unwrap();
+
+        let result = graph.walk_dfs_post_order(3, |_, _| Ok(()));
+
+        assert!(result.is_ok());
+        let (visited, cycles, times, edge_types) = result.unwrap();
+        assert!(visited.all());
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(edge_types[&(2, 0)], EdgeType::Back);
+        assert!(times.iter().all(|(d, f)| 0 < *d && d < f));
+    }
+
+    #[test]
+    fn walk_dfs_post_order_visits_children_first() {
+        let graph = Graph::from_dot("digraph { A -> B -> C; A -> C }").unwrap();
+        let mut order = Vec::new();
+
+        graph
+            .walk_dfs_post_order(0, |n, _| {
+                order.push(n);
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(order, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn walks_reuse_csr_until_graph_changes() {
+        let mut graph = Graph::from_dot("digraph { A -> B -> C }").unwrap();
+
+        let csr = graph.cached_csr(false);
+        graph.walk_dfs_post_order(0, |_, _| Ok(())).unwrap();
+        assert!(Rc::ptr_eq(&csr, &graph.cached_csr(false)));
+        assert!(!Rc::ptr_eq(&csr, &graph.cached_csr(true)));
+
+        graph.add_edge((0, 2), String::new());
+        let (_, _, _, edge_types) = graph.walk_dfs_post_order(0, |_, _| Ok(())).unwrap();
+        assert!(!Rc::ptr_eq(&csr, &graph.cached_csr(false)));
+        assert_eq!(edge_types[&(0, 2)], EdgeType::Forward);
+    }
+
+    #[test]
+    fn walk_dfs_post_order_classifies_edges() {
+        let graph = Graph::from_dot("digraph { A -> B -> C; A -> C; A -> D -> B }").unwrap();
+
+        let (_, _, _, edge_types) = graph.walk_dfs_post_order(0, |_, _| Ok(())).unwrap();
+
+        assert_eq!(edge_types[&(0, 1)], EdgeType::Tree);
+        assert_eq!(edge_types[&(1, 2)], EdgeType::Tree);
+        assert_eq!(edge_types[&(0, 2)], EdgeType::Forward);
+        assert_eq!(edge_types[&(0, 3)], EdgeType::Tree);
+        assert_eq!(edge_types[&(3, 1)], EdgeType::Cross);
+    }
+
+    #[test]
+    fn from_dot_reads_quoted_ids_attributes_and_chains() {
+        let graph = Graph::from_dot(
+            r#"digraph "Kategorie" {
+                // default attributes are ignored
+                node [shape=box];
+                Ludzie -> Poeci -> "Adam Mickiewicz" [label="Mickiewicz, Adam"];
+                "Adam Mickiewicz" [category="false", label="Adam \"Litwin\" Mickiewicz"]
+                /* several statements on one line */ Ludzie -> Pisarze; Pisarze -> Poeci
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(graph.verify_invariants(), Ok(()));
+
+        assert_eq!(graph.len(), 4);
+        assert_eq!(graph.edge_count(), 4);
+        let adam = graph
+            .find_vertex(&("Adam \"Litwin\" Mickiewicz".to_string(), false))
+            .unwrap();
+        let poeci = graph.find_vertex(&("Poeci".to_string(), true)).unwrap();
+        let ludzie = graph.find_vertex(&("Ludzie".to_string(), true)).unwrap();
+        assert_eq!(graph.get_edge_label(&(poeci, adam)), "Mickiewicz, Adam");
+        assert_eq!(graph.get_edge_label(&(ludzie, poeci)), "Mickiewicz, Adam");
+        assert_eq!(graph.roots(), vec![ludzie]);
+    }
+
+    #[test]
+    fn from_dot_rejects_unsupported_syntax() {
+        for source in &[
+            "strict digraph { A -> B }",
+            "graph { A -- B }",
+            "digraph { A -- B }",
+            "digraph { subgraph S { A -> B } }",
+            "digraph { A -> }",
+            "digraph { A [label] }",
+            "digraph { \"A -> B }",
+            "digraph { A -> B",
+        ] {
+            assert!(Graph::from_dot(source).is_err(), "accepted {}", source);
+        }
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn petgraph_roundtrip_keeps_structure() {
+        let graph = graph_with_edges(
+            &["A", "B", "C", "D", "E"],
+            &[(0, 1), (0, 2), (1, 3), (2, 3), (3, 4), (4, 1)],
+        );
+
+        let roundtrip = Graph::from_petgraph(graph.to_petgraph());
+        assert_eq!(roundtrip.verify_invariants(), Ok(()));
+
+        assert_eq!(roundtrip.len(), graph.len());
+        assert_eq!(roundtrip.edge_count(), graph.edge_count());
+        for n in 0..graph.len() {
+            assert_eq!(roundtrip.get_vertex_label(n), graph.get_vertex_label(n));
+            let mut expected = graph.direct_children(n).to_vec();
+            let mut actual = roundtrip.direct_children(n).to_vec();
+            expected.sort_unstable();
+            actual.sort_unstable();
+            assert_eq!(actual, expected);
+            for r in expected {
+                assert_eq!(
+                    roundtrip.get_edge_label(&(n, r)),
+                    graph.get_edge_label(&(n, r))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn from_edges_builds_graph() {
+        let edge = |from: &str, label: &str, to: &str, to_is_category: bool| {
+            (
+                from.to_string(),
+                true,
+                label.to_string(),
+                to.to_string(),
+                to_is_category,
+            )
+        };
+        let graph = Graph::from_edges(vec![
+            edge("Ludzie", "Poeci", "Poeci", true),
+            edge("Poeci", "Mickiewicz, Adam", "Adam Mickiewicz", false),
+        ]);
+
+        assert_eq!(graph.verify_invariants(), Ok(()));
+        assert_eq!(graph.len(), 3);
+        assert_eq!(graph.edge_count(), 2);
+        let poeci = graph.find_vertex(&("Poeci".to_string(), true)).unwrap();
+        let adam = graph
+            .find_vertex(&("Adam Mickiewicz".to_string(), false))
+            .unwrap();
+        assert_eq!(graph.get_edge_label(&(poeci, adam)), "Mickiewicz, Adam");
+        assert_eq!(graph.roots(), vec![0]);
+    }
+
+    #[test]
+    fn load_from_csv_reads_quoted_fields() {
+        let csv = "from_label,is_from_cat,edge_label,to_label,is_to_cat\n\
+                   Ludzie,1,Poeci,Poeci,1\n\
+                   Poeci,1,\"Mickiewicz, Adam\",Adam Mickiewicz,0\n";
+
+        let graph = Graph::load_from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(graph.verify_invariants(), Ok(()));
+
+        assert_eq!(graph.len(), 3);
+        let poeci = graph.find_vertex(&("Poeci".to_string(), true)).unwrap();
+        let adam = graph
+            .find_vertex(&("Adam Mickiewicz".to_string(), false))
+            .unwrap();
+        assert_eq!(graph.get_edge_label(&(poeci, adam)), "Mickiewicz, Adam");
+        assert!(Graph::load_from_csv("a,2,b,c,0".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn export_csv_quotes_fields() {
+        let csv = "Poeci,1,\"Mickiewicz, Adam\",\"Adam \"\"Litwin\"\" Mickiewicz\",0\n";
+        let graph = Graph::load_from_csv(csv.as_bytes()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let nodes = dir.path().join("nodes.csv");
+        let edges = dir.path().join("edges.csv");
+
+        graph.export_csv(&nodes, &edges).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&nodes).unwrap(),
+            "id,label,is_category\n0,Poeci,true\n1,\"Adam \"\"Litwin\"\" Mickiewicz\",false\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&edges).unwrap(),
+            "from_id,to_id,label\n0,1,\"Mickiewicz, Adam\"\n"
+        );
+    }
+
+    #[test]
+    fn dfs_iter_yields_reachable_nodes_once() {
+        let graph =
+            Graph::from_dot("digraph { A; B; C; D; E; A -> B -> D; A -> C -> D -> A }").unwrap();
+
+        assert_eq!(graph.dfs_iter(0).collect::<Vec<_>>(), vec![0, 1, 3, 2]);
+        assert_eq!(
+            graph.dfs_post_order_iter(0).collect::<Vec<_>>(),
+            vec![3, 1, 2, 0]
+        );
+        assert_eq!(graph.dfs_iter(4).collect::<Vec<_>>(), vec![4]);
+    }
+
+    #[test]
+    fn bfs_iter_yields_nodes_in_breadth_first_order() {
+        let graph =
+            Graph::from_dot("digraph { A; B; C; D; E; F; A -> B -> D -> E; A -> C -> D -> A }")
+                .unwrap();
+
+        assert_eq!(
+            graph.bfs_iter(0).collect::<Vec<_>>(),
+            vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 3)]
+        );
+        assert_eq!(graph.bfs_iter(5).collect::<Vec<_>>(), vec![(5, 0)]);
+    }
+
+    #[test]
+    fn enumerate_simple_paths_truncates_long_lists() {
+        // chain of 14 diamonds has 2^14 paths from its first to its last node
+        let mut edges = Vec::new();
+        for i in 0..14 {
+            let (top, left, right, bottom) = (3 * i, 3 * i + 1, 3 * i + 2, 3 * i + 3);
+            edges.extend_from_slice(&[(top, left), (top, right), (left, bottom), (right, bottom)]);
+        }
+        let labels: Vec<String> = (0..=42).map(|n| n.to_string()).collect();
+        let labels: Vec<&str> = labels.iter().map(|l| l.as_str()).collect();
+        let graph = graph_with_edges(&labels, &edges);
+
+        let (paths, truncated) = graph.enumerate_simple_paths(0, 6, 4);
+        assert_eq!(paths.len(), 4);
+        assert!(!truncated);
+        assert!(paths.iter().all(|p| p.len() == 5 && p[0] == 0 && p[4] == 6));
+
+        let (paths, truncated) = graph.enumerate_simple_paths(0, 42, 28);
+        assert_eq!(paths.len(), MAX_ENUMERATED_PATHS);
+        assert!(truncated);
+    }
+
+    #[test]
+    fn pagerank_sums_to_one() {
+        // A -> B -> C, A -> C, D
+        let graph = graph_with_edges(&["A", "B", "C", "D"], &[(0, 1), (1, 2), (0, 2)]);
+
+        let ranks = graph.pagerank(0.85, 1e-10, 100);
+
+        assert!((ranks.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        assert!(ranks[2] > ranks[1] && ranks[1] > ranks[0]);
+        assert!((ranks[0] - ranks[3]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn export_gml_escapes_labels() {
+        let graph = Graph::from_edges(vec![(
+            "Poeci".to_string(),
+            true,
+            "Mickiewicz, \"Litwin\"".to_string(),
+            "Adam Mickiewicz".to_string(),
+            false,
+        )]);
+        let mut gml = Vec::new();
+
+        graph.export_gml(&mut gml).unwrap();
+
+        assert_eq!(
+            String::from_utf8(gml).unwrap(),
+            "graph [\n  directed 1\n  \
+             node [ id 0 label \"Poeci\" is_category 1 ]\n  \
+             node [ id 1 label \"Adam Mickiewicz\" is_category 0 ]\n  \
+             edge [ source 0 target 1 label \"Mickiewicz, &quot;Litwin&quot;\" ]\n]\n"
+        );
+    }
+
+    #[test]
+    fn mutations_keep_invariants() {
+        // A -> B -> C -> D, A -> C, E
+        let mut graph = graph_with_edges(
+            &["A", "B", "C", "D", "E"],
+            &[(0, 1), (1, 2), (2, 3), (0, 2)],
+        );
+        assert_eq!(graph.verify_invariants(), Ok(()));
+
+        assert!(graph.reverse_edge(0, 2));
+        assert_eq!(graph.verify_invariants(), Ok(()));
+        graph.remove_edge(&(2, 0));
+        assert_eq!(graph.verify_invariants(), Ok(()));
+        graph.retain_edges(|from, _, _| from != 2);
+        assert_eq!(graph.verify_invariants(), Ok(()));
+        assert_eq!(graph.remove_isolated_nodes(), 2);
+        assert_eq!(graph.verify_invariants(), Ok(()));
+        assert_eq!(graph.contract_paths(None), 1);
+        assert_eq!(graph.len(), 2);
+        assert_eq!(graph.verify_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn contract_paths_keeps_given_node() {
+        let mut graph = Graph::from_dot("digraph { A -> B -> C -> D }").unwrap();
+        let b = graph.find_vertex(&("B".to_string(), true)).unwrap();
+        assert_eq!(graph.contract_paths(Some(b)), 1);
+        assert!(graph.find_vertex(&("B".to_string(), true)).is_some());
+        assert!(graph.find_vertex(&("C".to_string(), true)).is_none());
+    }
+
+    #[test]
+    fn visit_reachable_in_topological_order_skips_other_nodes() {
+        let graph = Graph::from_dot("digraph { A -> B -> C; D -> C; E -> F -> E }").unwrap();
+        let label = |n: Nd| graph.get_vertex_label(n).0.clone();
+        let a = graph.find_vertex(&("A".to_string(), true)).unwrap();
+        let mut visited = Vec::new();
+        graph
+            .visit_reachable_in_topological_order(a, |n| {
+                visited.push(label(n));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(visited, vec!["C", "B", "A"]);
+
+        let e = graph.find_vertex(&("E".to_string(), true)).unwrap();
+        let err = graph
+            .visit_reachable_in_topological_order(e, |_| Ok(()))
+            .unwrap_err();
+        assert_eq!(err.downcast_ref::<CycleError>().unwrap().unvisited.len(), 2);
+    }
+
+    #[test]
+    fn export_statistics_json_writes_graph_metrics() {
+        let graph = Graph::from_dot("digraph { A -> B -> C; A -> C }").unwrap();
+        let a = graph.find_vertex(&("A".to_string(), true)).unwrap();
+        let mut json = Vec::new();
+        graph.export_statistics_json(a, &mut json).unwrap();
+
+        let stats: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        assert_eq!(stats["node_count"], 3);
+        assert_eq!(stats["edge_count"], 3);
+        assert_eq!(stats["root_label"], "A");
+        assert_eq!(stats["max_depth"], 1);
+        assert_eq!(stats["cycle_count"], 0);
+        assert_eq!(stats["top_out_degree"][0], serde_json::json!(["A", 2]));
+    }
+
+    #[test]
+    fn export_gexf_writes_node_attributes() {
+        let mut graph = Graph::from_dot("digraph { A -> B }").unwrap();
+        let b = graph.find_vertex(&("B".to_string(), true)).unwrap();
+        graph.set_node_attribute(b, CITE_COUNT, "3".to_string());
+        graph.set_node_attribute(b, LAST_MODIFIED, "2020-05-01T10:00:00Z".to_string());
+        let mut gexf = Vec::new();
+        graph.export_gexf(&mut gexf).unwrap();
+        let gexf = String::from_utf8(gexf).unwrap();
+
+        assert!(gexf.contains(r#"<attribute id="cite_count" title="cite_count" type="integer"/>"#));
+        assert!(gexf.contains(r#"<attvalue for="cite_count" value="3"/>"#));
+        assert!(gexf.contains(r#"<attvalue for="last_modified" value="2020-05-01T10:00:00Z"/>"#));
+        assert!(!gexf.contains(r#"<attvalue for="article_count""#));
+    }
+
+    #[test]
+    fn ordered_list_in_category_page_sets_edge_order() {
+        let text = "# [[Kategoria:Poeci]]\n# [[Kategoria:Ludzie]]";
+        let parsed = parse(text);
+        let mut extractor = CategoryExtractor {
+            normalizer: Normalizer::with_category_prefixes(&["Kategoria"]),
+            ..Default::default()
+        };
+        extractor.set_site("Romantycy");
+        extractor.set_is_category(true);
+        extractor.extract(&parsed);
+
+        let graph = &extractor.graph;
+        let site = graph.find_vertex(&("Romantycy".to_string(), true)).unwrap();
+        let ludzie = graph.find_vertex(&("Ludzie".to_string(), true)).unwrap();
+        assert_eq!(graph.get_edge_order(&(ludzie, site)), Some(2));
+    }
+
+    #[test]
+    fn link_to_non_category_is_skipped() {
+        let text = "[[Category:Poeci]] [[Kategoria:Ludzie]]";
+        let parsed = parse(text);
+        let mut extractor = CategoryExtractor {
+            normalizer: Normalizer::with_category_prefixes(&["Kategoria"]),
+            ..Default::default()
+        };
+        extractor.set_site("Adam Mickiewicz");
+        extractor.extract(&parsed);
+
+        assert_eq!(extractor.graph.len(), 2);
+        assert_eq!(extractor.new_edges, 1);
+        assert!(extractor
+            .graph
+            .find_vertex(&("Ludzie".to_string(), true))
+            .is_some());
+    }
+
+    #[test]
+    fn ordinals_keep_empty_sort_keys() {
+        let text = "[[Kategoria:Poeci|Mickiewicz, Adam]] [[Kategoria:Ludzie]] [[Kategoria:Polacy|]] [[ Kategoria:Romantycy | ]]";
+        let parsed = parse(text);
+        let mut extractor = CategoryExtractor {
+            normalizer: Normalizer::with_category_prefixes(&["Kategoria"]),
+            ..Default::default()
+        };
+        extractor.graph.add_vertex(("Pusta".to_string(), true));
+        extractor.set_site("Adam Mickiewicz");
+        extractor.set_source(text);
+        extractor.extract(&parsed);
+
+        // ordinals follow nodes renumbered when empty categories are removed
+        assert_eq!(extractor.prune_empty_categories(), 1);
+
+        let graph = &extractor.graph;
+        let site = graph
+            .find_vertex(&("Adam Mickiewicz".to_string(), false))
+            .unwrap();
+        let poeci = graph.find_vertex(&("Poeci".to_string(), true)).unwrap();
+        let ludzie = graph.find_vertex(&("Ludzie".to_string(), true)).unwrap();
+        let polacy = graph.find_vertex(&("Polacy".to_string(), true)).unwrap();
+        let romantycy = graph.find_vertex(&("Romantycy".to_string(), true)).unwrap();
+        assert_eq!(extractor.ordinals[&(poeci, site)], "Mickiewicz, Adam");
+        assert!(!extractor.ordinals.contains_key(&(ludzie, site)));
+        assert_eq!(extractor.ordinals[&(polacy, site)], "");
+        assert_eq!(extractor.ordinals[&(romantycy, site)], "");
+        assert_eq!(graph.get_edge_label(&(ludzie, site)), "Adam Mickiewicz");
+    }
+}
@@ -1,18 +1,45 @@
-use crate::text_extractor::TextExtractor;
-use parse_wiki_text::{self, Node, Output};
+use crate::text_extractor::{strip_template_artifacts, TemplateResolver, TextExtractor};
+use crate::{csv_field, sanitize_file_name};
+use parse_wiki_text::{self, DefinitionListItemType, Node, Output, Parameter};
 use serde::Serialize;
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::fmt;
+use std::io::{self, Write};
+
+/// Metadata key used for attributions given as definition list details.
+const SOURCE_KEY: &str = "Źródło";
+
+/// Metadata keys holding author of a cite.
+const AUTHOR_KEYS: [&str; 2] = ["Autor", "Author"];
+
+/// Name of the template used for cites given with their attribution.
+const CYTAT_TEMPLATE: &str = "Cytat";
+
+/// Shorter paragraphs are most likely not cites.
+const MIN_PARAGRAPH_LENGTH: usize = 20;
 
 #[derive(Serialize, Default)]
 pub struct Cites {
     pub cites: Vec<Cite>,
+    /// Remove `{{...}}` left in texts of extracted cites.
+    #[serde(skip)]
+    pub clean_templates: bool,
+    /// Expands templates found in cite texts (by default they are skipped).
+    #[serde(skip)]
+    pub template_resolver: Option<Box<TemplateResolver>>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, PartialEq, Eq)]
 pub struct Cite {
     pub text: String,
     pub sections: Vec<String>,
     pub meta: Vec<MetaData>,
+    pub alternatives: Vec<String>,
+    /// 0-based index of the cite within its section.
+    pub position: usize,
+    /// Title of the article the cite was extracted from.
+    pub source_article: String,
 }
 
 impl Cite {
@@ -21,11 +48,89 @@ impl Cite {
             text,
             sections: Vec::new(),
             meta: Vec::new(),
+            alternatives: Vec::new(),
+            position: 0,
+            source_article: String::new(),
+        }
+    }
+
+    /// Top-level section name (`sections[0]` is the article title).
+    pub fn primary_section(&self) -> Option<&str> {
+        self.sections.get(1).map(|s| s.as_str())
+    }
+
+    /// Second-level section name.
+    pub fn subsection(&self) -> Option<&str> {
+        self.sections.get(2).map(|s| s.as_str())
+    }
+
+    /// Sections joined with `/`, each sanitized to be a single path component.
+    pub fn section_path(&self) -> String {
+        let components: Vec<String> = self
+            .sections
+            .iter()
+            .map(|s| match sanitize_file_name(s).as_str() {
+                "" | "." | ".." => "_".to_string(),
+                name => name.to_string(),
+            })
+            .collect();
+        components.join("/")
+    }
+
+    /// Format cite as a tab separated Anki card: author on the front, text on the back.
+    ///
+    /// Article title is used when there's no author metadata.
+    pub fn to_anki_card(&self) -> String {
+        let author = self
+            .meta
+            .iter()
+            .find(|m| AUTHOR_KEYS.contains(&m.key.trim()))
+            .map(|m| m.value.as_str())
+            .or_else(|| self.sections.first().map(|s| s.as_str()))
+            .unwrap_or("");
+        format!("{}\t{}", anki_field(author), anki_field(&self.text))
+    }
+
+    /// Replace runs of whitespace in text with a single space and trim it.
+    pub fn normalize_whitespace(&mut self) {
+        let words: Vec<&str> = self.text.split_whitespace().collect();
+        self.text = words.join(" ");
+    }
+
+    pub fn with_normalized_whitespace(mut self) -> Self {
+        self.normalize_whitespace();
+        self
+    }
+
+    /// Format cite with every line (also of multi-line text) indented by `indent` spaces.
+    pub fn display_indented(&self, f: &mut fmt::Formatter, indent: usize) -> fmt::Result {
+        let mut lines = Vec::new();
+        if !self.source_article.is_empty() && self.sections.first() != Some(&self.source_article) {
+            lines.push(format!("Artykuł: {}", self.source_article));
+        }
+        if !self.sections.is_empty() {
+            lines.push(format!("Sekcja: {}", self.sections.join(" / ")));
+        }
+        for MetaData { key, value, .. } in &self.meta {
+            lines.push(format!("{}: {}", key, value));
+        }
+        lines.push(String::new());
+        lines.push(self.text.clone());
+        for alternative in &self.alternatives {
+            lines.push(format!("* {}", alternative));
+        }
+        for line in lines.iter().flat_map(|l| l.split('\n')) {
+            if line.is_empty() {
+                writeln!(f)?;
+            } else {
+                writeln!(f, "{:indent$}{}", "", line, indent = indent)?;
+            }
         }
+        fmt::Result::Ok(())
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord)]
 pub struct MetaData {
     pub key: String,
     pub value: String,
@@ -38,15 +143,53 @@ impl MetaData {
     }
 }
 
+/// Cites are ordered by sections, then by text.
+/// Remaining fields only break ties, so that ordering is consistent with `Eq`.
+impl Ord for Cite {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.sections
+            .cmp(&other.sections)
+            .then_with(|| self.text.cmp(&other.text))
+            .then_with(|| self.meta.cmp(&other.meta))
+            .then_with(|| self.alternatives.cmp(&other.alternatives))
+            .then_with(|| self.position.cmp(&other.position))
+            .then_with(|| self.source_article.cmp(&other.source_article))
+    }
+}
+
+impl PartialOrd for Cite {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl fmt::Display for Cite {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if !self.sections.is_empty() {
-            writeln!(f, "Sekcja: {}", self.sections.join(" / "))?;
-        }
-        for MetaData { key, value, .. } in &self.meta {
-            writeln!(f, "{}: {}", key, value)?;
+        self.display_indented(f, 0)
+    }
+}
+
+/// Cites separated by `"---\n"`.
+impl fmt::Display for Cites {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.with_separator("---\n").fmt(f)
+    }
+}
+
+/// Displays cites with custom separator between them.
+pub struct CitesFormatter<'a> {
+    pub cites: &'a Cites,
+    pub separator: &'a str,
+}
+
+impl fmt::Display for CitesFormatter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, cite) in self.cites.cites.iter().enumerate() {
+            if i > 0 {
+                f.write_str(self.separator)?;
+            }
+            cite.display_indented(f, 4)?;
         }
-        writeln!(f, "\n{}", self.text)?;
         fmt::Result::Ok(())
     }
 }
@@ -54,34 +197,447 @@ impl fmt::Display for Cite {
 impl Cites {
     pub fn extract_cites(&mut self, parsed: &Output, title: &str) {
         let mut breadcrumbs = Breadcrumbs::new(title);
+        let mut position = 0;
         for node in &parsed.nodes {
             match node {
                 Node::UnorderedList { items, .. } => {
                     for item in items {
-                        let mut extr = TextExtractor::new();
+                        let mut extr = self.text_extractor();
                         extr.descend_lists = false;
                         extr.extract_item_text(&item);
-                        let mut cite = Cite::new(extr.result());
+                        let mut cite = self.new_cite(extr.result(), title);
                         cite.sections = breadcrumbs.stack.clone();
+                        cite.position = position;
+                        position += 1;
 
-                        let mut meta_reader = MetaReader::default();
-                        meta_reader.read(&item.nodes);
+                        let mut meta_reader = MetaReader {
+                            template_resolver: self.template_resolver.as_deref(),
+                            ..Default::default()
+                        };
+                        meta_reader.read(&item.nodes, 1);
                         cite.meta = meta_reader.meta;
+                        cite.alternatives = meta_reader.alternatives;
 
                         self.cites.push(cite);
                     }
                 }
 
+                Node::DefinitionList { items, .. } => {
+                    let mut current: Option<Cite> = None;
+                    for item in items {
+                        let mut extr = self.text_extractor();
+                        extr.extract_dl_text(item);
+                        let text = extr.result().trim().to_string();
+                        match item.type_ {
+                            DefinitionListItemType::Term => {
+                                if let Some(cite) = current.take() {
+                                    self.cites.push(cite);
+                                }
+                                let mut cite = self.new_cite(text, title);
+                                cite.sections = breadcrumbs.stack.clone();
+                                cite.position = position;
+                                position += 1;
+                                current = Some(cite);
+                            }
+                            DefinitionListItemType::Details => {
+                                if let Some(cite) = current.as_mut() {
+                                    cite.meta.push(MetaData::new(
+                                        SOURCE_KEY.to_string(),
+                                        text,
+                                        vec![],
+                                    ));
+                                }
+                            }
+                        }
+                    }
+                    if let Some(cite) = current {
+                        self.cites.push(cite);
+                    }
+                }
+
                 Node::Heading { level, nodes, .. } => {
-                    let mut extr = TextExtractor::new();
+                    let mut extr = self.text_extractor();
                     extr.extract_nodes_text(&nodes);
-                    breadcrumbs.update(*level, extr.result())
+                    breadcrumbs.update(*level, extr.result());
+                    position = 0;
+                }
+
+                Node::Template {
+                    name, parameters, ..
+                } if is_template(name, CYTAT_TEMPLATE) => {
+                    if let Some(mut cite) = extract_cytat_template(parameters) {
+                        if self.clean_templates {
+                            cite.text = strip_template_artifacts(&cite.text);
+                        }
+                        cite.sections = breadcrumbs.stack.clone();
+                        cite.position = position;
+                        cite.source_article = title.to_string();
+                        position += 1;
+                        self.cites.push(cite);
+                    }
                 }
 
                 _ => {}
             }
         }
     }
+
+    /// Take text of every `tag` element (e.g. `poem`, or any tag when `None`)
+    /// found outside headings as a cite.
+    pub fn extract_tagged(&mut self, parsed: &Output, title: &str, tag: Option<&str>) {
+        let mut breadcrumbs = Breadcrumbs::new(title);
+        let mut position = 0;
+        for node in &parsed.nodes {
+            if let Node::Heading { level, nodes, .. } = node {
+                let mut heading = self.text_extractor();
+                heading.extract_nodes_text(nodes);
+                breadcrumbs.update(*level, heading.result());
+                position = 0;
+                continue;
+            }
+            let mut extr = self.text_extractor();
+            extr.extract_tagged(std::slice::from_ref(node), tag);
+            let text = extr.result().trim().to_string();
+            if !text.is_empty() {
+                let mut cite = self.new_cite(text, title);
+                cite.sections = breadcrumbs.stack.clone();
+                cite.position = position;
+                position += 1;
+                self.cites.push(cite);
+            }
+        }
+    }
+
+    /// Fallback for articles having headings but no unordered lists:
+    /// take every paragraph at least `MIN_PARAGRAPH_LENGTH` characters long as a cite.
+    pub fn extract_paragraphs(&mut self, parsed: &Output, title: &str) {
+        let has_heading = parsed
+            .nodes
+            .iter()
+            .any(|n| matches!(n, Node::Heading { .. }));
+        let has_list = parsed
+            .nodes
+            .iter()
+            .any(|n| matches!(n, Node::UnorderedList { .. }));
+        if !has_heading || has_list {
+            return;
+        }
+        let mut breadcrumbs = Breadcrumbs::new(title);
+        let mut position = 0;
+        // push_paragraph borrows self mutably, so extractors can't borrow resolver from it
+        let resolver = self.template_resolver.take();
+        let mut extr = TextExtractor::new();
+        extr.template_resolver = resolver.as_deref();
+        for node in &parsed.nodes {
+            match node {
+                Node::Heading { level, nodes, .. } => {
+                    self.push_paragraph(&mut extr, &breadcrumbs, &mut position);
+                    let mut heading = TextExtractor::new();
+                    heading.template_resolver = resolver.as_deref();
+                    heading.extract_nodes_text(nodes);
+                    breadcrumbs.update(*level, heading.result());
+                    position = 0;
+                }
+
+                Node::ParagraphBreak { .. }
+                | Node::DefinitionList { .. }
+                | Node::OrderedList { .. }
+                | Node::Preformatted { .. }
+                | Node::Table { .. }
+                | Node::HorizontalDivider { .. } => {
+                    self.push_paragraph(&mut extr, &breadcrumbs, &mut position)
+                }
+
+                _ => extr.extract_node_text(node),
+            }
+        }
+        self.push_paragraph(&mut extr, &breadcrumbs, &mut position);
+        self.template_resolver = resolver;
+    }
+
+    fn text_extractor(&self) -> TextExtractor<'_> {
+        let mut extr = TextExtractor::new();
+        extr.template_resolver = self.template_resolver.as_deref();
+        extr
+    }
+
+    fn new_cite(&self, text: String, title: &str) -> Cite {
+        let mut cite = if self.clean_templates {
+            Cite::new(strip_template_artifacts(&text))
+        } else {
+            Cite::new(text)
+        };
+        cite.source_article = title.to_string();
+        cite
+    }
+
+    fn push_paragraph(
+        &mut self,
+        extr: &mut TextExtractor,
+        breadcrumbs: &Breadcrumbs,
+        position: &mut usize,
+    ) {
+        let text = extr.result().trim().to_string();
+        extr.text.clear();
+        if text.chars().count() >= MIN_PARAGRAPH_LENGTH {
+            let mut cite = self.new_cite(text, &breadcrumbs.stack[0]);
+            cite.sections = breadcrumbs.stack.clone();
+            cite.position = *position;
+            *position += 1;
+            self.cites.push(cite);
+        }
+    }
+}
+
+impl Cites {
+    /// Format cites with `separator` between them (e.g. `"\n\n"` or `""`).
+    pub fn with_separator<'a>(&'a self, separator: &'a str) -> CitesFormatter<'a> {
+        CitesFormatter {
+            cites: self,
+            separator,
+        }
+    }
+
+    /// Write cites as pretty printed JSON without building the whole string in memory.
+    pub fn to_json_writer<W: Write>(&self, w: &mut W) -> serde_json::Result<()> {
+        serde_json::to_writer_pretty(w, self)
+    }
+
+    /// Write cites as CSV with `sections,text,meta` columns.
+    pub fn to_csv_writer<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "sections,text,meta")?;
+        for cite in &self.cites {
+            let meta: Vec<String> = cite
+                .meta
+                .iter()
+                .map(|m| format!("{}: {}", m.key, m.value))
+                .collect();
+            writeln!(
+                w,
+                "{},{},{}",
+                csv_field(&cite.sections.join(" / ")),
+                csv_field(&cite.text),
+                csv_field(&meta.join("; "))
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write cites as Markdown block quotes, with a heading for every new section.
+    pub fn to_markdown_writer<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut last_sections: Option<&Vec<String>> = None;
+        for cite in &self.cites {
+            if last_sections != Some(&cite.sections) {
+                writeln!(w, "## {}\n", cite.sections.join(" / "))?;
+                last_sections = Some(&cite.sections);
+            }
+            for line in cite.text.lines() {
+                writeln!(w, "> {}", line)?;
+            }
+            for MetaData { key, value, .. } in &cite.meta {
+                writeln!(w, ">\n> — *{}:* {}", key.trim(), value)?;
+            }
+            writeln!(w)?;
+        }
+        Ok(())
+    }
+
+    /// Write cites as an HTML fragment.
+    pub fn to_html_writer<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let mut last_sections: Option<&Vec<String>> = None;
+        for cite in &self.cites {
+            if last_sections != Some(&cite.sections) {
+                writeln!(w, "<h2>{}</h2>", html_escape(&cite.sections.join(" / ")))?;
+                last_sections = Some(&cite.sections);
+            }
+            writeln!(w, "<blockquote>")?;
+            let lines: Vec<String> = cite.text.lines().map(html_escape).collect();
+            writeln!(w, "<p>{}</p>", lines.join("<br>"))?;
+            if !cite.meta.is_empty() {
+                writeln!(w, "<dl>")?;
+                for MetaData { key, value, .. } in &cite.meta {
+                    writeln!(
+                        w,
+                        "<dt>{}</dt><dd>{}</dd>",
+                        html_escape(key.trim()),
+                        html_escape(value)
+                    )?;
+                }
+                writeln!(w, "</dl>")?;
+            }
+            writeln!(w, "</blockquote>")?;
+        }
+        Ok(())
+    }
+
+    /// Links from metadata of all cites, without duplicates, in order of appearance.
+    pub fn all_links(&self) -> Vec<&str> {
+        let mut seen = HashSet::new();
+        self.links().filter(|link| seen.insert(*link)).collect()
+    }
+
+    /// Set of links from metadata of all cites.
+    pub fn unique_links(&self) -> HashSet<&str> {
+        self.links().collect()
+    }
+
+    fn links(&self) -> impl Iterator<Item = &str> {
+        self.cites
+            .iter()
+            .flat_map(|c| c.meta.iter())
+            .flat_map(|m| m.links.iter())
+            .map(|l| l.as_str())
+    }
+
+    /// Anki deck in tab separated format, one card per cite.
+    pub fn to_anki_deck(&self) -> String {
+        let mut deck = String::from("#separator:tab\n#html:true\n");
+        for cite in &self.cites {
+            deck.push_str(&cite.to_anki_card());
+            deck.push('\n');
+        }
+        deck
+    }
+
+    /// Keep only cites having metadata with given key (case insensitive).
+    pub fn filter_by_meta_key(&mut self, key: &str) {
+        let key = key.to_lowercase();
+        self.cites
+            .retain(|c| c.meta.iter().any(|m| m.key.trim().to_lowercase() == key));
+    }
+
+    /// Keep only cites having metadata with given key (case insensitive) and value.
+    pub fn filter_by_meta_value(&mut self, key: &str, value: &str) {
+        let key = key.to_lowercase();
+        self.cites.retain(|c| {
+            c.meta
+                .iter()
+                .any(|m| m.key.trim().to_lowercase() == key && m.value == value)
+        });
+    }
+
+    /// Split cites into those with and without metadata (attribution).
+    ///
+    /// Template resolver is dropped, as it can't be shared between the results.
+    pub fn partition_by_attribution(self) -> (Cites, Cites) {
+        let clean_templates = self.clean_templates;
+        let (with_meta, without_meta) = self.cites.into_iter().partition(|c| !c.meta.is_empty());
+        (
+            Cites {
+                cites: with_meta,
+                clean_templates,
+                template_resolver: None,
+            },
+            Cites {
+                cites: without_meta,
+                clean_templates,
+                template_resolver: None,
+            },
+        )
+    }
+}
+
+impl IntoIterator for Cites {
+    type Item = Cite;
+    type IntoIter = std::vec::IntoIter<Cite>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cites.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Cites {
+    type Item = &'a Cite;
+    type IntoIter = std::slice::Iter<'a, Cite>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cites.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Cites {
+    type Item = &'a mut Cite;
+    type IntoIter = std::slice::IterMut<'a, Cite>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.cites.iter_mut()
+    }
+}
+
+impl Extend<Cite> for Cites {
+    fn extend<T: IntoIterator<Item = Cite>>(&mut self, iter: T) {
+        self.cites.extend(iter)
+    }
+}
+
+fn nodes_text(nodes: &[Node]) -> String {
+    let mut extr = TextExtractor::new();
+    for node in nodes {
+        extr.extract_node_text(node);
+    }
+    extr.result().trim().to_string()
+}
+
+/// Template names are case insensitive on the first letter.
+fn is_template(name: &[Node], expected: &str) -> bool {
+    let name = nodes_text(name);
+    let mut chars = name.chars();
+    let mut expected_chars = expected.chars();
+    match (chars.next(), expected_chars.next()) {
+        (Some(a), Some(b)) => {
+            a.to_lowercase().eq(b.to_lowercase()) && chars.as_str() == expected_chars.as_str()
+        }
+        _ => false,
+    }
+}
+
+/// Cite from `{{Cytat|tekst=...|autor=...|źródło=...|link=...}}`.
+///
+/// Returns `None` when there's no (or empty) `tekst` parameter.
+fn extract_cytat_template(parameters: &[Parameter]) -> Option<Cite> {
+    let mut text = None;
+    let mut meta = Vec::new();
+    for parameter in parameters {
+        let name = match &parameter.name {
+            Some(name) => nodes_text(name).to_lowercase(),
+            None => continue,
+        };
+        let value = nodes_text(&parameter.value);
+        if value.is_empty() {
+            continue;
+        }
+        match name.as_str() {
+            "tekst" => text = Some(value),
+            "autor" => meta.push(MetaData::new(AUTHOR_KEYS[0].to_string(), value, vec![])),
+            "źródło" => meta.push(MetaData::new(SOURCE_KEY.to_string(), value, vec![])),
+            "link" => meta.push(MetaData::new(
+                "Link".to_string(),
+                value.clone(),
+                vec![value],
+            )),
+            _ => {}
+        }
+    }
+    text.map(|text| {
+        let mut cite = Cite::new(text);
+        cite.meta = meta;
+        cite
+    })
+}
+
+/// Anki field can't contain tabs and new lines (HTML is allowed).
+fn anki_field(value: &str) -> String {
+    html_escape(value.trim())
+        .replace('\t', " ")
+        .replace('\n', "<br>")
+}
+
+fn html_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
 }
 
 struct Breadcrumbs {
@@ -109,25 +665,51 @@ impl Breadcrumbs {
     }
 }
 
+/// Reads nested list items of a cite.
+///
+/// Items of the form `key: value` become metadata, other second-level items
+/// are treated as alternative formulations (e.g. translations) of the cite.
+/// Deeper items without a key are appended (as new lines) to the metadata
+/// value or alternative they are nested under.
 #[derive(Default)]
-struct MetaReader {
+struct MetaReader<'a> {
     meta: Vec<MetaData>,
+    alternatives: Vec<String>,
+    template_resolver: Option<&'a TemplateResolver>,
 }
 
-impl MetaReader {
-    pub fn read(&mut self, items: &Vec<Node>) {
+impl MetaReader<'_> {
+    /// # Return value
+    /// Texts of items (with their nested text) that are neither metadata nor alternatives.
+    pub fn read(&mut self, items: &Vec<Node>, depth: u8) -> Vec<String> {
+        let mut texts = Vec::new();
         for item in items {
             match item {
                 Node::UnorderedList { items, .. } => {
                     for item in items {
                         let mut extr = TextExtractor::new();
+                        extr.descend_lists = false;
+                        extr.template_resolver = self.template_resolver;
                         extr.extract_item_text(item);
                         let text = extr.result();
                         let mut parts: Vec<&str> = text.splitn(2, ":").collect();
                         if parts.len() == 2 {
                             let second = parts.pop().unwrap().trim().to_string();
                             let first = parts.pop().unwrap().to_string();
+                            let i = self.meta.len();
                             self.meta.push(MetaData::new(first, second, vec![]));
+                            let nested = self.read(&item.nodes, depth + 1);
+                            append_lines(&mut self.meta[i].value, nested);
+                        } else if depth == 1 {
+                            let i = self.alternatives.len();
+                            self.alternatives.push(text.trim().to_string());
+                            let nested = self.read(&item.nodes, depth + 1);
+                            append_lines(&mut self.alternatives[i], nested);
+                        } else {
+                            let mut text = text.trim().to_string();
+                            let nested = self.read(&item.nodes, depth + 1);
+                            append_lines(&mut text, nested);
+                            texts.push(text);
                         }
                     }
                 }
@@ -135,5 +717,293 @@ impl MetaReader {
                 _ => {}
             }
         }
+        texts
+    }
+}
+
+fn append_lines(text: &mut String, lines: Vec<String>) {
+    for line in lines {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    #[test]
+    fn consecutive_lists_share_section() {
+        let parsed = parse("== A ==\n* one\n* two\n\n* three\n== B ==\n* four");
+        assert_eq!(
+            parsed
+                .nodes
+                .iter()
+                .filter(|n| matches!(n, Node::UnorderedList { .. }))
+                .count(),
+            3
+        );
+        let mut cites = Cites::default();
+        cites.extract_cites(&parsed, "T");
+
+        let found: Vec<(&str, Option<&str>, usize)> = cites
+            .cites
+            .iter()
+            .map(|c| (c.text.as_str(), c.primary_section(), c.position))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("one", Some("A"), 0),
+                ("two", Some("A"), 1),
+                ("three", Some("A"), 2),
+                ("four", Some("B"), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn cytat_template_becomes_cite() {
+        let parsed = parse(
+            "== A ==\n* one\n{{Cytat|tekst=Veni, vidi, vici.|autor=Juliusz Cezar|źródło=Plutarch|link=https://example.org}}",
+        );
+        let mut cites = Cites::default();
+        cites.extract_cites(&parsed, "T");
+
+        assert_eq!(cites.cites.len(), 2);
+        let cite = &cites.cites[1];
+        assert_eq!(cite.text, "Veni, vidi, vici.");
+        assert_eq!(cite.primary_section(), Some("A"));
+        assert_eq!(cite.position, 1);
+        assert_eq!(cite.source_article, "T");
+        let meta: Vec<(&str, &str)> = cite
+            .meta
+            .iter()
+            .map(|m| (m.key.as_str(), m.value.as_str()))
+            .collect();
+        assert_eq!(
+            meta,
+            vec![
+                ("Autor", "Juliusz Cezar"),
+                ("Źródło", "Plutarch"),
+                ("Link", "https://example.org"),
+            ]
+        );
+        assert_eq!(cite.meta[2].links, vec!["https://example.org".to_string()]);
+    }
+
+    #[test]
+    fn template_resolver_expands_templates_in_cites() {
+        let parsed = parse("* Rok {{r|1834}}, {{nieznany}}.\n** Autor: {{r|Mickiewicz}}");
+        let mut cites = Cites {
+            template_resolver: Some(Box::new(|name, parameters| {
+                if name != "r" {
+                    return None;
+                }
+                let mut extr = TextExtractor::new();
+                extr.extract_nodes_text(&parameters[0].value);
+                Some(extr.result())
+            })),
+            ..Default::default()
+        };
+        cites.extract_cites(&parsed, "T");
+
+        assert_eq!(cites.cites[0].text, "Rok 1834, .");
+        assert_eq!(cites.cites[0].meta[0].value, "Mickiewicz");
+    }
+
+    #[test]
+    fn section_path_sanitizes_slashes_in_headings() {
+        let mut cites = Cites::default();
+        cites.extract_cites(
+            &parse("== Wojna/pokój ==\n=== .. ===\n* cite"),
+            "Lew Tołstoj",
+        );
+
+        assert_eq!(cites.cites[0].section_path(), "Lew Tołstoj/Wojna-pokój/_");
+    }
+
+    #[test]
+    fn cites_sort_by_sections_then_text_consistently_with_eq() {
+        let cite = |sections: &[&str], text: &str, position: usize| {
+            let mut cite = Cite::new(text.to_string());
+            cite.sections = sections.iter().map(|s| s.to_string()).collect();
+            cite.position = position;
+            cite
+        };
+        let mut cites = vec![
+            cite(&["T", "B"], "a", 0),
+            cite(&["T", "A"], "b", 0),
+            cite(&["T", "A"], "a", 1),
+            cite(&["T"], "z", 0),
+            cite(&["T", "A"], "a", 0),
+        ];
+        cites.sort();
+
+        let sorted: Vec<(&str, &str, usize)> = cites
+            .iter()
+            .map(|c| {
+                (
+                    c.sections.last().unwrap().as_str(),
+                    c.text.as_str(),
+                    c.position,
+                )
+            })
+            .collect();
+        assert_eq!(
+            sorted,
+            vec![
+                ("T", "z", 0),
+                ("A", "a", 0),
+                ("A", "a", 1),
+                ("A", "b", 0),
+                ("B", "a", 0),
+            ]
+        );
+        for a in &cites {
+            for b in &cites {
+                assert_eq!(a.cmp(b) == Ordering::Equal, a == b);
+                assert_eq!(a.cmp(b), b.cmp(a).reverse());
+            }
+        }
+    }
+
+    #[test]
+    fn definition_list_terms_become_cites_with_details_as_source() {
+        let parsed = parse(
+            "== A ==\n: orphan\n; Veni, vidi, vici.\n: Juliusz Cezar\n; Second\n: one\n: two\n; Lone",
+        );
+        assert!(matches!(parsed.nodes[1], Node::DefinitionList { .. }));
+        let mut cites = Cites::default();
+        cites.extract_cites(&parsed, "T");
+
+        let found: Vec<(&str, Option<&str>, usize)> = cites
+            .cites
+            .iter()
+            .map(|c| (c.text.as_str(), c.primary_section(), c.position))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("Veni, vidi, vici.", Some("A"), 0),
+                ("Second", Some("A"), 1),
+                ("Lone", Some("A"), 2),
+            ]
+        );
+        let meta: Vec<Vec<(&str, &str)>> = cites
+            .cites
+            .iter()
+            .map(|c| {
+                c.meta
+                    .iter()
+                    .map(|m| (m.key.as_str(), m.value.as_str()))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            meta,
+            vec![
+                vec![(SOURCE_KEY, "Juliusz Cezar")],
+                vec![(SOURCE_KEY, "one"), (SOURCE_KEY, "two")],
+                vec![],
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_items_are_kept_under_metadata_and_alternatives() {
+        let parsed = parse(
+            "* Cogito ergo sum.\n** Źródło: Zasady filozofii\n*** część I\n**** punkt 7\n** Myślę, więc jestem.\n*** przekład polski\n*** Autor: Kartezjusz",
+        );
+        let mut cites = Cites::default();
+        cites.extract_cites(&parsed, "T");
+
+        let cite = &cites.cites[0];
+        assert_eq!(cite.text, "Cogito ergo sum.");
+        let meta: Vec<(&str, &str)> = cite
+            .meta
+            .iter()
+            .map(|m| (m.key.as_str(), m.value.as_str()))
+            .collect();
+        assert_eq!(
+            meta,
+            vec![
+                ("Źródło", "Zasady filozofii\nczęść I\npunkt 7"),
+                ("Autor", "Kartezjusz"),
+            ]
+        );
+        assert_eq!(
+            cite.alternatives,
+            vec!["Myślę, więc jestem.\nprzekład polski".to_string()]
+        );
+    }
+
+    #[test]
+    fn display_indented_indents_every_line() {
+        struct Indented<'a>(&'a Cite);
+        impl fmt::Display for Indented<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                self.0.display_indented(f, 2)
+            }
+        }
+        let mut cite = Cite::new("first\nsecond".to_string());
+        cite.sections = vec!["T".to_string()];
+        cite.source_article = "T".to_string();
+        cite.meta = vec![MetaData::new("Autor".to_string(), "X".to_string(), vec![])];
+        cite.alternatives = vec!["other".to_string()];
+
+        assert_eq!(
+            Indented(&cite).to_string(),
+            "  Sekcja: T\n  Autor: X\n\n  first\n  second\n  * other\n"
+        );
+        assert_eq!(
+            cite.to_string(),
+            "Sekcja: T\nAutor: X\n\nfirst\nsecond\n* other\n"
+        );
+    }
+
+    #[test]
+    fn paragraph_cites_are_numbered_within_sections() {
+        let parsed = parse(
+            "== A ==\nPierwszy dość długi akapit.\n\nDrugi dość długi akapit.\n\nkrótki\n== B ==\nTrzeci dość długi akapit.",
+        );
+        let mut cites = Cites::default();
+        cites.extract_paragraphs(&parsed, "T");
+
+        let found: Vec<(&str, Option<&str>, usize)> = cites
+            .cites
+            .iter()
+            .map(|c| (c.text.as_str(), c.primary_section(), c.position))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("Pierwszy dość długi akapit.", Some("A"), 0),
+                ("Drugi dość długi akapit.", Some("A"), 1),
+                ("Trzeci dość długi akapit.", Some("B"), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn tagged_cites_are_taken_from_tag_contents() {
+        let parsed = parse(
+            "== A ==\n<poem>Pierwszy\nwiersz</poem>\n* zwykły cytat\n== B ==\nx <poem>Drugi</poem>",
+        );
+        let mut cites = Cites::default();
+        cites.extract_tagged(&parsed, "T", Some("poem"));
+
+        let found: Vec<(&str, Option<&str>, usize)> = cites
+            .cites
+            .iter()
+            .map(|c| (c.text.as_str(), c.primary_section(), c.position))
+            .collect();
+        assert_eq!(
+            found,
+            vec![("Pierwszy\nwiersz", Some("A"), 0), ("Drugi", Some("B"), 0)]
+        );
     }
 }
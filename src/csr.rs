@@ -0,0 +1,39 @@
+use crate::category_graph::Nd;
+
+/// Read only adjacency lists in Compressed Sparse Row layout.
+///
+/// Neighbours of node `n` are `indices[indptr[n]..indptr[n + 1]]`, so all of
+/// them are stored in one contiguous vector.
+#[derive(Debug, Default)]
+pub struct CsrGraph {
+    indptr: Vec<usize>,
+    indices: Vec<Nd>,
+}
+
+impl CsrGraph {
+    /// Build from neighbour lists of consecutive nodes.
+    pub fn from_adjacency<'a, I>(lists: I) -> CsrGraph
+    where
+        I: IntoIterator<Item = &'a [Nd]>,
+    {
+        let mut indptr = vec![0];
+        let mut indices = Vec::new();
+        for list in lists {
+            indices.extend_from_slice(list);
+            indptr.push(indices.len());
+        }
+        CsrGraph { indptr, indices }
+    }
+
+    pub fn len(&self) -> usize {
+        self.indptr.len() - 1
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn outgoing_neighbors(&self, n: Nd) -> &[Nd] {
+        &self.indices[self.indptr[n]..self.indptr[n + 1]]
+    }
+}
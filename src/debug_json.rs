@@ -0,0 +1,302 @@
+use parse_wiki_text::{
+    DefinitionListItem, DefinitionListItemType, ListItem, Node, Output, Parameter, TableCaption,
+    TableCell, TableCellType, TableRow, Warning,
+};
+use serde_json::{json, Value};
+
+/// Convert parser output to JSON.
+///
+/// `parse_wiki_text::Output` doesn't implement `Serialize`, so every node is
+/// converted by hand to an object tagged with its variant name in `type`.
+pub fn output_to_json(output: &Output) -> Value {
+    json!({
+        "nodes": nodes_to_json(&output.nodes),
+        "warnings": output.warnings.iter().map(warning_to_json).collect::<Vec<_>>(),
+    })
+}
+
+fn warning_to_json(warning: &Warning) -> Value {
+    json!({
+        "start": warning.start,
+        "end": warning.end,
+        "message": warning.message.message(),
+    })
+}
+
+fn nodes_to_json(nodes: &[Node]) -> Value {
+    Value::Array(nodes.iter().map(node_to_json).collect())
+}
+
+fn opt_nodes_to_json(nodes: &Option<Vec<Node>>) -> Value {
+    match nodes {
+        Some(nodes) => nodes_to_json(nodes),
+        None => Value::Null,
+    }
+}
+
+fn list_items_to_json(items: &[ListItem]) -> Value {
+    Value::Array(
+        items
+            .iter()
+            .map(|i| json!({"start": i.start, "end": i.end, "nodes": nodes_to_json(&i.nodes)}))
+            .collect(),
+    )
+}
+
+fn definition_items_to_json(items: &[DefinitionListItem]) -> Value {
+    Value::Array(
+        items
+            .iter()
+            .map(|i| {
+                let type_ = match i.type_ {
+                    DefinitionListItemType::Details => "Details",
+                    DefinitionListItemType::Term => "Term",
+                };
+                json!({
+                    "type": type_,
+                    "start": i.start,
+                    "end": i.end,
+                    "nodes": nodes_to_json(&i.nodes),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn parameters_to_json(parameters: &[Parameter]) -> Value {
+    Value::Array(
+        parameters
+            .iter()
+            .map(|p| {
+                json!({
+                    "start": p.start,
+                    "end": p.end,
+                    "name": opt_nodes_to_json(&p.name),
+                    "value": nodes_to_json(&p.value),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn captions_to_json(captions: &[TableCaption]) -> Value {
+    Value::Array(
+        captions
+            .iter()
+            .map(|c| {
+                json!({
+                    "start": c.start,
+                    "end": c.end,
+                    "attributes": opt_nodes_to_json(&c.attributes),
+                    "content": nodes_to_json(&c.content),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn cells_to_json(cells: &[TableCell]) -> Value {
+    Value::Array(
+        cells
+            .iter()
+            .map(|c| {
+                let type_ = match c.type_ {
+                    TableCellType::Heading => "Heading",
+                    TableCellType::Ordinary => "Ordinary",
+                };
+                json!({
+                    "type": type_,
+                    "start": c.start,
+                    "end": c.end,
+                    "attributes": opt_nodes_to_json(&c.attributes),
+                    "content": nodes_to_json(&c.content),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn rows_to_json(rows: &[TableRow]) -> Value {
+    Value::Array(
+        rows.iter()
+            .map(|r| {
+                json!({
+                    "start": r.start,
+                    "end": r.end,
+                    "attributes": nodes_to_json(&r.attributes),
+                    "cells": cells_to_json(&r.cells),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn node_to_json(node: &Node) -> Value {
+    match node {
+        Node::Bold { start, end } => json!({"type": "Bold", "start": start, "end": end}),
+        Node::BoldItalic { start, end } => {
+            json!({"type": "BoldItalic", "start": start, "end": end})
+        }
+        Node::Category {
+            start,
+            end,
+            ordinal,
+            target,
+        } => json!({
+            "type": "Category",
+            "start": start,
+            "end": end,
+            "ordinal": nodes_to_json(ordinal),
+            "target": target,
+        }),
+        Node::CharacterEntity {
+            start,
+            end,
+            character,
+        } => json!({
+            "type": "CharacterEntity",
+            "start": start,
+            "end": end,
+            "character": character.to_string(),
+        }),
+        Node::Comment { start, end } => json!({"type": "Comment", "start": start, "end": end}),
+        Node::DefinitionList { start, end, items } => json!({
+            "type": "DefinitionList",
+            "start": start,
+            "end": end,
+            "items": definition_items_to_json(items),
+        }),
+        Node::EndTag { start, end, name } => {
+            json!({"type": "EndTag", "start": start, "end": end, "name": name})
+        }
+        Node::ExternalLink { start, end, nodes } => json!({
+            "type": "ExternalLink",
+            "start": start,
+            "end": end,
+            "nodes": nodes_to_json(nodes),
+        }),
+        Node::Heading {
+            start,
+            end,
+            level,
+            nodes,
+        } => json!({
+            "type": "Heading",
+            "start": start,
+            "end": end,
+            "level": level,
+            "nodes": nodes_to_json(nodes),
+        }),
+        Node::HorizontalDivider { start, end } => {
+            json!({"type": "HorizontalDivider", "start": start, "end": end})
+        }
+        Node::Image {
+            start,
+            end,
+            target,
+            text,
+        } => json!({
+            "type": "Image",
+            "start": start,
+            "end": end,
+            "target": target,
+            "text": nodes_to_json(text),
+        }),
+        Node::Italic { start, end } => json!({"type": "Italic", "start": start, "end": end}),
+        Node::Link {
+            start,
+            end,
+            target,
+            text,
+        } => json!({
+            "type": "Link",
+            "start": start,
+            "end": end,
+            "target": target,
+            "text": nodes_to_json(text),
+        }),
+        Node::MagicWord { start, end } => {
+            json!({"type": "MagicWord", "start": start, "end": end})
+        }
+        Node::OrderedList { start, end, items } => json!({
+            "type": "OrderedList",
+            "start": start,
+            "end": end,
+            "items": list_items_to_json(items),
+        }),
+        Node::ParagraphBreak { start, end } => {
+            json!({"type": "ParagraphBreak", "start": start, "end": end})
+        }
+        Node::Parameter {
+            start,
+            end,
+            name,
+            default,
+        } => json!({
+            "type": "Parameter",
+            "start": start,
+            "end": end,
+            "name": nodes_to_json(name),
+            "default": opt_nodes_to_json(default),
+        }),
+        Node::Preformatted { start, end, nodes } => json!({
+            "type": "Preformatted",
+            "start": start,
+            "end": end,
+            "nodes": nodes_to_json(nodes),
+        }),
+        Node::Redirect { start, end, target } => {
+            json!({"type": "Redirect", "start": start, "end": end, "target": target})
+        }
+        Node::StartTag { start, end, name } => {
+            json!({"type": "StartTag", "start": start, "end": end, "name": name})
+        }
+        Node::Table {
+            start,
+            end,
+            attributes,
+            captions,
+            rows,
+        } => json!({
+            "type": "Table",
+            "start": start,
+            "end": end,
+            "attributes": nodes_to_json(attributes),
+            "captions": captions_to_json(captions),
+            "rows": rows_to_json(rows),
+        }),
+        Node::Tag {
+            start,
+            end,
+            name,
+            nodes,
+        } => json!({
+            "type": "Tag",
+            "start": start,
+            "end": end,
+            "name": name,
+            "nodes": nodes_to_json(nodes),
+        }),
+        Node::Template {
+            start,
+            end,
+            name,
+            parameters,
+        } => json!({
+            "type": "Template",
+            "start": start,
+            "end": end,
+            "name": nodes_to_json(name),
+            "parameters": parameters_to_json(parameters),
+        }),
+        Node::Text { start, end, value } => {
+            json!({"type": "Text", "start": start, "end": end, "value": value})
+        }
+        Node::UnorderedList { start, end, items } => json!({
+            "type": "UnorderedList",
+            "start": start,
+            "end": end,
+            "items": list_items_to_json(items),
+        }),
+    }
+}
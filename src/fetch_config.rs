@@ -0,0 +1,243 @@
+use parse_wiki_text::{Configuration, ConfigurationSource};
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+// Mirrors what https://github.com/portstrom/fetch_mediawiki_configuration
+// extracts from the `meta=siteinfo` API query.
+const SITEINFO_PROPS: &str =
+    "general|namespaces|namespacealiases|extensiontags|magicwords|protocols";
+
+const CATEGORY_NAMESPACE: i32 = 14;
+const FILE_NAMESPACE: i32 = 6;
+
+#[derive(Deserialize)]
+struct SiteInfoResponse {
+    query: SiteInfo,
+}
+
+#[derive(Deserialize)]
+pub struct SiteInfo {
+    general: General,
+    namespaces: std::collections::HashMap<String, Namespace>,
+    namespacealiases: Vec<NamespaceAlias>,
+    extensiontags: Vec<String>,
+    magicwords: Vec<MagicWord>,
+    protocols: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct General {
+    linktrail: String,
+}
+
+#[derive(Deserialize)]
+struct Namespace {
+    id: i32,
+    name: String,
+    canonical: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NamespaceAlias {
+    id: i32,
+    alias: String,
+}
+
+#[derive(Deserialize)]
+struct MagicWord {
+    name: String,
+    aliases: Vec<String>,
+}
+
+#[derive(Debug)]
+struct LinkTrailError(String);
+
+impl Display for LinkTrailError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unsupported link trail: {}", self.0)
+    }
+}
+
+impl Error for LinkTrailError {}
+
+/// Build parser configuration from siteinfo of a MediaWiki site.
+///
+/// # Arguments
+/// * `api_url` - URL of `api.php`, e.g. `https://pl.wikiquote.org/w/api.php`
+pub fn fetch_wiki_config(api_url: &str) -> Result<Configuration, Box<dyn Error>> {
+    build_config(&fetch_site_info(api_url)?)
+}
+
+/// Query siteinfo of a MediaWiki site.
+pub fn fetch_site_info(api_url: &str) -> Result<SiteInfo, Box<dyn Error>> {
+    let response = ureq::get(api_url)
+        .query("action", "query")
+        .query("meta", "siteinfo")
+        .query("siprop", SITEINFO_PROPS)
+        .query("format", "json")
+        .query("formatversion", "2")
+        .call()?;
+    let info: SiteInfoResponse = serde_json::from_reader(response.into_reader())?;
+    Ok(info.query)
+}
+
+/// Build parser configuration from siteinfo.
+pub fn build_config(info: &SiteInfo) -> Result<Configuration, Box<dyn Error>> {
+    let category_namespaces = category_namespaces(info);
+    let file_namespaces = namespace_names(info, FILE_NAMESPACE);
+    let extension_tags: Vec<String> = info
+        .extensiontags
+        .iter()
+        .map(|t| t.trim_start_matches('<').trim_end_matches('>').to_string())
+        .collect();
+    let link_trail = link_trail_characters(&info.general.linktrail)?;
+    let mut magic_words = Vec::new();
+    let mut redirect_magic_words = Vec::new();
+    for word in &info.magicwords {
+        for alias in &word.aliases {
+            if word.name == "redirect" {
+                redirect_magic_words.push(alias.trim_start_matches('#').to_string());
+            } else if alias.len() > 4 && alias.starts_with("__") && alias.ends_with("__") {
+                magic_words.push(alias[2..alias.len() - 2].to_string());
+            }
+        }
+    }
+
+    Ok(Configuration::new(&ConfigurationSource {
+        category_namespaces: &as_strs(&category_namespaces),
+        extension_tags: &as_strs(&extension_tags),
+        file_namespaces: &as_strs(&file_namespaces),
+        link_trail: &link_trail,
+        magic_words: &as_strs(&magic_words),
+        protocols: &as_strs(&info.protocols),
+        redirect_magic_words: &as_strs(&redirect_magic_words),
+    }))
+}
+
+/// Lowercase names of the category namespace, including aliases.
+pub fn category_namespaces(info: &SiteInfo) -> Vec<String> {
+    namespace_names(info, CATEGORY_NAMESPACE)
+}
+
+fn as_strs(v: &[String]) -> Vec<&str> {
+    v.iter().map(|s| s.as_str()).collect()
+}
+
+/// Lowercase names and aliases of namespace `id`.
+fn namespace_names(info: &SiteInfo, id: i32) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for ns in info.namespaces.values().filter(|ns| ns.id == id) {
+        names.push(ns.name.to_lowercase());
+        if let Some(canonical) = &ns.canonical {
+            names.push(canonical.to_lowercase());
+        }
+    }
+    for alias in info.namespacealiases.iter().filter(|a| a.id == id) {
+        names.push(alias.alias.to_lowercase());
+    }
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Expand character class of link trail regex like `/^([a-zóąćęłńśźż]+)(.*)$/sDu`
+/// into the list of characters it matches.
+///
+/// Empty class (`/^()(.*)$/sD`, used by sites without link trails) matches nothing.
+fn link_trail_characters(link_trail: &str) -> Result<String, Box<dyn Error>> {
+    if link_trail.contains("^()") {
+        return Ok(String::new());
+    }
+    let start = link_trail.find("^([");
+    let end = link_trail.find("]+)");
+    let class = match (start, end) {
+        (Some(start), Some(end)) if start + 3 <= end => &link_trail[start + 3..end],
+        _ => return Err(Box::new(LinkTrailError(link_trail.to_string()))),
+    };
+    let chars: Vec<char> = class.chars().collect();
+    let mut result = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        if i + 2 < chars.len() && chars[i + 1] == '-' {
+            for c in chars[i]..=chars[i + 2] {
+                result.push(c);
+            }
+            i += 3;
+        } else {
+            result.push(chars[i]);
+            i += 1;
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parse_wiki_text::Node;
+
+    const SITE_INFO: &str = r##"{
+        "general": {"linktrail": "/^([a-zóąćęłńśźż]+)(.*)$/sDu"},
+        "namespaces": {
+            "6": {"id": 6, "name": "Plik", "canonical": "File"},
+            "14": {"id": 14, "name": "Kategoria", "canonical": "Category"}
+        },
+        "namespacealiases": [{"id": 6, "alias": "Grafika"}],
+        "extensiontags": ["<ref>", "<nowiki>"],
+        "magicwords": [
+            {"name": "redirect", "aliases": ["#PATRZ", "#REDIRECT"]},
+            {"name": "notoc", "aliases": ["__BEZSPISU__", "__NOTOC__"]}
+        ],
+        "protocols": ["http://", "https://"]
+    }"##;
+
+    fn site_info(link_trail: &str) -> SiteInfo {
+        let mut info: SiteInfo = serde_json::from_str(SITE_INFO).unwrap();
+        info.general.linktrail = link_trail.to_string();
+        info
+    }
+
+    #[test]
+    fn build_config_from_site_info() {
+        let info = site_info("/^([a-zóąćęłńśźż]+)(.*)$/sDu");
+        assert_eq!(category_namespaces(&info), vec!["category", "kategoria"]);
+        let config = build_config(&info).unwrap();
+
+        let text = "[[Kategoria:Poeci]] [[Grafika:x.png]] [[kot]]ów";
+        let parsed = config.parse(text);
+        assert!(parsed.warnings.is_empty());
+        match &parsed.nodes[0] {
+            Node::Category { target, .. } => assert_eq!(*target, "Kategoria:Poeci"),
+            node => panic!("expected category, got {:?}", node),
+        }
+        assert!(matches!(parsed.nodes[2], Node::Image { .. }));
+        // link trail is a part of the link
+        assert_eq!(parsed.nodes.len(), 5);
+        match &parsed.nodes[4] {
+            Node::Link { end, .. } => assert_eq!(*end, text.len()),
+            node => panic!("expected link, got {:?}", node),
+        }
+    }
+
+    #[test]
+    fn empty_link_trail_matches_nothing() {
+        assert_eq!(link_trail_characters("/^()(.*)$/sD").unwrap(), "");
+        let config = build_config(&site_info("/^()(.*)$/sD")).unwrap();
+
+        let parsed = config.parse("[[kot]]ów");
+        assert!(matches!(
+            parsed.nodes[..],
+            [Node::Link { .. }, Node::Text { value: "ów", .. }]
+        ));
+    }
+
+    #[test]
+    fn link_trail_ranges_are_expanded() {
+        assert_eq!(
+            link_trail_characters("/^([a-dó]+)(.*)$/sDu").unwrap(),
+            "abcdó"
+        );
+        assert!(link_trail_characters("/^(.*)$/").is_err());
+    }
+}
@@ -0,0 +1,817 @@
+pub mod category_graph;
+pub mod cite_extractor;
+pub mod csr;
+pub mod debug_json;
+pub mod fetch_config;
+pub mod revision_timestamps;
+pub mod text_extractor;
+pub mod xml_namespace;
+
+#[cfg(test)]
+mod test_util;
+
+use crate::category_graph::{
+    CategoryExtractor, CycleEvent, Ed, EdgeType, Graph, Nd, Normalizer, CITE_COUNT, LAST_MODIFIED,
+};
+use crate::cite_extractor::Cites;
+use crate::revision_timestamps::{TimestampRecorder, Timestamps};
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::io::{BufRead, BufReader};
+use std::time::{Duration, Instant};
+
+use git2::{Oid, Repository, Signature};
+use parse_mediawiki_dump::Page;
+use parse_wiki_text::{Configuration, Output, Warning};
+use radix_fmt::radix_36;
+use regex::Regex;
+
+#[macro_use]
+extern crate lazy_static;
+
+// Typos allowed in root category given by search
+const SEARCH_MAX_EDIT_DISTANCE: usize = 2;
+
+// Signature of commits when none is given
+const DEFAULT_AUTHOR_NAME: &str = "WikiQuotes";
+const DEFAULT_AUTHOR_EMAIL: &str = "anonymous@pl.wikiquote.org";
+
+lazy_static! {
+    static ref TAG_MATCH: Regex = Regex::new(r"<(/?)([a-zA-Z]+)\b[^<>]*?(/?)>").unwrap();
+}
+
+/// Storage for preprocessed page text, which parse output may borrow from.
+#[derive(Default)]
+pub struct RetryBuffers {
+    stages: Vec<String>,
+}
+
+/// Parse page text, retrying with progressively stricter preprocessing when
+/// the parser reports warnings.
+///
+/// # Return value
+/// Output with the fewest warnings, warnings reported for the original text
+/// and the text the output was parsed from (positions in output refer to it).
+pub fn parse_with_retry<'a>(
+    config: &Configuration,
+    text: &'a str,
+    buffers: &'a mut RetryBuffers,
+) -> (Output<'a>, Vec<Warning>, &'a str) {
+    let mut best = config.parse(text);
+    if best.warnings.is_empty() {
+        return (best, vec![], text);
+    }
+    let warnings = best
+        .warnings
+        .iter()
+        .map(|w| Warning {
+            start: w.start,
+            end: w.end,
+            message: w.message,
+        })
+        .collect();
+
+    let replaced = replace_unusual_unicode(text);
+    let stripped = strip_unmatched_tags(&replaced);
+    buffers.stages = vec![replaced, stripped];
+    let buffers: &'a RetryBuffers = buffers;
+
+    let mut best_text = text;
+    for stage in &buffers.stages {
+        if best.warnings.is_empty() {
+            break;
+        }
+        let retry = config.parse(stage);
+        if retry.warnings.len() < best.warnings.len() {
+            best = retry;
+            best_text = stage;
+        }
+    }
+    (best, warnings, best_text)
+}
+
+fn replace_unusual_unicode(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| match c {
+            '\u{00A0}' | '\u{2000}'..='\u{200A}' | '\u{202F}' => Some(' '),
+            '\u{2028}' | '\u{2029}' => Some('\n'),
+            '\u{200B}'..='\u{200F}' | '\u{FEFF}' => None,
+            c if c.is_control() && c != '\n' && c != '\t' => None,
+            c => Some(c),
+        })
+        .collect()
+}
+
+/// Elements without content, which never have a closing tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Remove opening and closing tags which have no counterpart.
+///
+/// Tags are matched like in HTML: closing tag matches the nearest open element
+/// of the same name and closes elements opened after it. Void elements and
+/// self-closing tags are always kept.
+fn strip_unmatched_tags(text: &str) -> String {
+    let tags: Vec<regex::Captures> = TAG_MATCH.captures_iter(text).collect();
+    let mut unmatched = vec![false; tags.len()];
+    let mut open: Vec<(String, usize)> = Vec::new();
+    for (i, cap) in tags.iter().enumerate() {
+        let name = cap[2].to_lowercase();
+        if &cap[3] == "/" || VOID_ELEMENTS.contains(&name.as_str()) {
+            continue;
+        }
+        if &cap[1] != "/" {
+            open.push((name, i));
+        } else if let Some(pos) = open.iter().rposition(|(n, _)| *n == name) {
+            for (_, j) in open.drain(pos..).skip(1) {
+                unmatched[j] = true;
+            }
+        } else {
+            unmatched[i] = true;
+        }
+    }
+    for (_, j) in open {
+        unmatched[j] = true;
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for (cap, _) in tags.iter().zip(unmatched).filter(|(_, u)| *u) {
+        let m = cap.get(0).unwrap();
+        result.push_str(&text[last..m.start()]);
+        last = m.end();
+    }
+    result.push_str(&text[last..]);
+    result
+}
+
+pub fn log_warnings(title: &str, warnings: &[Warning]) {
+    for w in warnings {
+        tracing::warn!(title, start = w.start, end = w.end, "{}", w.message);
+    }
+}
+
+/// Store graph reachable from `root` as git trees, with cites of articles as blobs.
+///
+/// Every node gets a tree with `cat.txt` or `art.txt` blob holding its label,
+/// subtrees of its children and cites numbered in base 36.
+///
+/// # Return value
+/// Hash of the tree of `root`, or `CycleError` if there is a loop reachable
+/// from `root`.
+pub fn write_category_tree(
+    repo: &Repository,
+    graph: &Graph,
+    root: Nd,
+    cites: &HashMap<Nd, Cites>,
+) -> Result<Oid, Box<dyn Error>> {
+    let mut hashes: HashMap<Nd, Oid> = HashMap::new();
+
+    graph.visit_reachable_in_topological_order(root, |n| {
+        let v_label = graph.get_vertex_label(n);
+        let name_blob = repo.blob(v_label.0.as_bytes())?;
+        let mut builder = repo.treebuilder(None)?;
+        let blob_name = if v_label.1 { "cat.txt" } else { "art.txt" };
+        builder.insert(blob_name, name_blob, 0o100644)?;
+        for out in graph.direct_children(n) {
+            let name = get_git_file_name(graph, n, *out);
+            let h = hashes.get(out).expect("Children should be already added");
+            builder.insert(name, *h, 0o040000)?;
+        }
+        if let Some(cites) = cites.get(&n) {
+            for (i, cite) in cites.into_iter().enumerate() {
+                let blob = repo.blob(format!("{}", cite).as_bytes())?;
+                let cname = format!("{}.txt", radix_36(i + 1));
+                builder.insert(cname, blob, 0o100644)?;
+            }
+        }
+        let tree = builder.write()?;
+        hashes.insert(n, tree);
+        Ok(())
+    })?;
+
+    Ok(hashes[&root])
+}
+
+pub fn get_git_file_name(graph: &Graph, from: Nd, to: Nd) -> String {
+    let el = graph.get_edge_label(&(from, to));
+    let name = if !el.is_empty() {
+        el
+    } else {
+        graph.get_vertex_label(to).0.as_ref()
+    };
+    sanitize_file_name(name)
+}
+
+pub fn sanitize_file_name(name: &str) -> String {
+    name.replace("/", "-")
+}
+
+/// Quote CSV field according to RFC 4180.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NoRootCategoryError;
+
+impl Display for NoRootCategoryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "No root category found!")
+    }
+}
+
+impl Error for NoRootCategoryError {}
+
+#[derive(Debug)]
+pub struct MediawikiParseError(pub parse_mediawiki_dump::Error);
+
+impl Display for MediawikiParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Mediawiki parse error: ")?;
+        self.0.fmt(f)
+    }
+}
+
+impl Error for MediawikiParseError {}
+
+/// Category graph (with loops cut), its root and cites of its articles.
+pub struct CategoryData {
+    pub graph: Graph,
+    pub root: Nd,
+    /// Loops found below `root`, cut by removing their last edges.
+    pub cycles: Vec<CycleEvent>,
+    pub cites: HashMap<Nd, Cites>,
+}
+
+/// How cites of articles are extracted.
+#[derive(Debug, Default, Clone)]
+pub struct CiteOptions {
+    pub clean_templates: bool,
+    pub extract_paragraphs: bool,
+    pub require_attribution: bool,
+    pub normalize_whitespace: bool,
+    /// Take contents of these tags (e.g. `poem`) as cites instead of list items.
+    pub tag: Option<String>,
+}
+
+/// Progress of `WikiquoteClient::process`.
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    /// Articles with cites extracted so far.
+    pub articles: usize,
+    pub cites: usize,
+    pub elapsed: Duration,
+}
+
+type ProgressCallback = Box<dyn FnMut(&Progress)>;
+
+/// Whole pipeline: extracting categories and cites from a dump and storing them in git.
+///
+/// ```no_run
+/// # use wikiquote::WikiquoteClient;
+/// # fn run(
+/// #     config: parse_wiki_text::Configuration,
+/// #     config_again: parse_wiki_text::Configuration,
+/// #     repo: &git2::Repository,
+/// # ) -> Result<(), Box<dyn std::error::Error>> {
+/// let dump = std::io::BufReader::new(std::fs::File::open("dump.xml")?);
+/// let cat_data = WikiquoteClient::new(config)
+///     .with_graph_capacity(10_000)
+///     .process(dump)?;
+/// let commit = WikiquoteClient::new(config_again).write_git(cat_data, repo)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct WikiquoteClient {
+    config: Configuration,
+    normalizer: Normalizer,
+    graph_capacity: usize,
+    graph: Option<Graph>,
+    search: String,
+    contract_paths: bool,
+    prune_empty: bool,
+    prune_unreachable: bool,
+    cite_options: CiteOptions,
+    progress: Option<(usize, ProgressCallback)>,
+    signatures: Option<(Signature<'static>, Signature<'static>)>,
+}
+
+impl WikiquoteClient {
+    pub fn new(config: Configuration) -> WikiquoteClient {
+        WikiquoteClient {
+            config,
+            normalizer: Normalizer::default(),
+            graph_capacity: 0,
+            graph: None,
+            search: String::new(),
+            contract_paths: false,
+            prune_empty: false,
+            prune_unreachable: false,
+            cite_options: CiteOptions::default(),
+            progress: None,
+            signatures: None,
+        }
+    }
+
+    /// Reserve space for `n` vertices of the category graph.
+    pub fn with_graph_capacity(mut self, n: usize) -> Self {
+        self.graph_capacity = n;
+        self
+    }
+
+    /// Use already built category graph (e.g. imported from CSV) instead of
+    /// extracting one from the dump.
+    pub fn with_graph(mut self, graph: Graph) -> Self {
+        self.graph = Some(graph);
+        self
+    }
+
+    /// Use `normalizer` (e.g. knowing category namespaces of the site) for page titles.
+    pub fn with_normalizer(mut self, normalizer: Normalizer) -> Self {
+        self.normalizer = normalizer;
+        self
+    }
+
+    /// Use category `name` (or the most similar one) as the root instead of the
+    /// first category without parents.
+    pub fn with_search(mut self, name: &str) -> Self {
+        self.search = name.to_string();
+        self
+    }
+
+    /// Collapse chains of categories having one parent and one child (root is kept).
+    pub fn with_contract_paths(mut self, contract_paths: bool) -> Self {
+        self.contract_paths = contract_paths;
+        self
+    }
+
+    /// Remove categories without articles and subcategories.
+    pub fn with_prune_empty(mut self, prune_empty: bool) -> Self {
+        self.prune_empty = prune_empty;
+        self
+    }
+
+    /// Remove nodes not reachable from the root.
+    pub fn with_prune_unreachable(mut self, prune_unreachable: bool) -> Self {
+        self.prune_unreachable = prune_unreachable;
+        self
+    }
+
+    pub fn with_cite_options(mut self, cite_options: CiteOptions) -> Self {
+        self.cite_options = cite_options;
+        self
+    }
+
+    /// Call `f` every `interval` articles processed (and once at the end).
+    pub fn with_progress(mut self, interval: usize, f: impl FnMut(&Progress) + 'static) -> Self {
+        self.progress = Some((interval.max(1), Box::new(f)));
+        self
+    }
+
+    /// Author and committer of the commit made by `write_git`.
+    pub fn with_signatures(
+        mut self,
+        author: Signature<'static>,
+        committer: Signature<'static>,
+    ) -> Self {
+        self.signatures = Some((author, committer));
+        self
+    }
+
+    pub fn config(&self) -> &Configuration {
+        &self.config
+    }
+
+    pub fn normalizer(&self) -> &Normalizer {
+        &self.normalizer
+    }
+
+    /// Read dump once, building category graph and extracting cites of articles.
+    ///
+    /// Graph is then simplified, its root is chosen and loops below the root are cut.
+    /// Only cites of articles left in the graph are kept. Their number and time of
+    /// the last revision of every page are stored as `CITE_COUNT` and `LAST_MODIFIED`
+    /// node attributes.
+    pub fn process(mut self, dump: impl BufRead) -> Result<CategoryData, Box<dyn Error>> {
+        let _span = tracing::info_span!("process").entered();
+        let mut category_extractor = CategoryExtractor::default();
+        category_extractor.normalizer = std::mem::take(&mut self.normalizer);
+        let imported = self.graph.is_some();
+        match self.graph.take() {
+            Some(graph) => category_extractor.set_graph(graph),
+            None => category_extractor.graph = Graph::with_capacity(self.graph_capacity),
+        }
+        let mut cites_by_title: HashMap<String, Cites> = HashMap::new();
+        let mut last_modified: HashMap<(String, bool), String> = HashMap::new();
+        let mut cite_count = 0;
+        let started = Instant::now();
+        let timestamps = Timestamps::default();
+        let dump = BufReader::new(TimestampRecorder::new(dump, timestamps.clone()));
+        for result in parse_mediawiki_dump::parse(dump) {
+            let page: Page = result.map_err(MediawikiParseError)?;
+            let (site_name, is_category) = category_extractor
+                .normalizer
+                .normalize_category_name(&page.title);
+            if let Some(timestamp) = timestamps.borrow_mut().pop_front().flatten() {
+                last_modified.insert((site_name.clone(), is_category), timestamp);
+            }
+            let mut buffers = RetryBuffers::default();
+            let (parsed, warnings, text) = parse_with_retry(&self.config, &page.text, &mut buffers);
+            log_warnings(&page.title, &warnings);
+            if !is_category && is_article(&page) {
+                let cites = self.page_cites(&parsed, &page.title);
+                cite_count += cites.cites.len();
+                cites_by_title.insert(site_name.clone(), cites);
+                self.report_progress(cites_by_title.len(), cite_count, &started, false);
+            }
+            if !imported {
+                category_extractor.set_site(site_name);
+                category_extractor.set_is_category(is_category);
+                category_extractor.set_source(text);
+                category_extractor.extract(&parsed);
+            }
+        }
+        self.report_progress(cites_by_title.len(), cite_count, &started, true);
+        tracing::info!(
+            "Found {} category links ({} repeated).",
+            category_extractor.new_edges,
+            category_extractor.repeated_edges
+        );
+
+        let (mut graph, root, cycles) = self.simplify(category_extractor)?;
+        for n in 0..graph.len() {
+            if let Some(timestamp) = last_modified.remove(graph.get_vertex_label(n)) {
+                graph.set_node_attribute(n, LAST_MODIFIED, timestamp);
+            }
+        }
+        let cites: HashMap<Nd, Cites> = cites_by_title
+            .into_iter()
+            .filter_map(|(title, cites)| graph.find_vertex(&(title, false)).map(|n| (n, cites)))
+            .collect();
+        for (n, article_cites) in &cites {
+            graph.set_node_attribute(*n, CITE_COUNT, article_cites.cites.len().to_string());
+        }
+        Ok(CategoryData {
+            graph,
+            root,
+            cycles,
+            cites,
+        })
+    }
+
+    fn report_progress(&mut self, articles: usize, cites: usize, started: &Instant, done: bool) {
+        if let Some((interval, f)) = &mut self.progress {
+            if (articles % *interval == 0) != done {
+                f(&Progress {
+                    articles,
+                    cites,
+                    elapsed: started.elapsed(),
+                });
+            }
+        }
+    }
+
+    /// Simplify graph of `category_extractor`, choose its root and cut loops below it.
+    fn simplify(
+        &self,
+        mut category_extractor: CategoryExtractor,
+    ) -> Result<(Graph, Nd, Vec<CycleEvent>), Box<dyn Error>> {
+        // root is chosen before the graph is simplified, so that it's not contracted
+        let root_label = self
+            .find_root(&category_extractor.graph)
+            .map(|n| category_extractor.graph.get_vertex_label(n).clone());
+
+        if self.contract_paths {
+            let root = root_label
+                .as_ref()
+                .and_then(|label| category_extractor.graph.find_vertex(label));
+            let contracted = category_extractor.graph.contract_paths(root);
+            tracing::info!("Contracted {} nodes.", contracted);
+        }
+
+        if self.prune_empty {
+            let pruned = category_extractor.prune_empty_categories();
+            tracing::info!("Pruned {} empty categories.", pruned);
+        }
+
+        let isolated = category_extractor.graph.remove_isolated_nodes();
+        if isolated > 0 {
+            tracing::info!("Removed {} isolated nodes.", isolated);
+        }
+
+        let mut graph = category_extractor.graph;
+        let mut root = root_label
+            .and_then(|label| graph.find_vertex(&label))
+            .ok_or(NoRootCategoryError)?;
+        if self.prune_unreachable {
+            root = graph.prune_unreachable(root)[root].unwrap();
+        }
+        let (_, cycles, _, edge_types) = graph.walk_dfs_post_order(root, |_, _| Ok(()))?;
+        log_cross_edges(&graph, &edge_types);
+        for c in &cycles {
+            graph.remove_edge(&(c.from_id, c.to_id));
+        }
+        Ok((graph, root, cycles))
+    }
+
+    /// Category given with `with_search` (or the most similar one) or the first root.
+    fn find_root(&self, graph: &Graph) -> Option<Nd> {
+        if !self.search.is_empty() {
+            let search = (self.search.clone(), true);
+            if let Some(n) = graph.find_vertex(&search) {
+                return Some(n);
+            }
+            let fuzzy = graph.find_vertices_fuzzy(&self.search, true, SEARCH_MAX_EDIT_DISTANCE);
+            if let Some((n, _)) = fuzzy.first() {
+                tracing::info!(
+                    "Category '{}' not found, using '{}'.",
+                    self.search,
+                    graph.get_vertex_label(*n).0
+                );
+                return Some(*n);
+            }
+        }
+        graph.roots().first().copied()
+    }
+
+    /// Cites of page `title` parsed into `parsed`, extracted according to cite options.
+    pub fn page_cites(&self, parsed: &Output, title: &str) -> Cites {
+        let options = &self.cite_options;
+        let mut cites = Cites {
+            clean_templates: options.clean_templates,
+            ..Default::default()
+        };
+        match &options.tag {
+            Some(tag) => cites.extract_tagged(parsed, title, Some(tag.as_str())),
+            None => cites.extract_cites(parsed, title),
+        }
+        if options.extract_paragraphs {
+            cites.extract_paragraphs(parsed, title);
+        }
+        if options.require_attribution {
+            cites = cites.partition_by_attribution().0;
+        }
+        if options.normalize_whitespace {
+            for cite in &mut cites {
+                cite.normalize_whitespace();
+            }
+        }
+        cites
+    }
+
+    /// Store categories and cites of `cat_data` in `repo`, creating `master` branch.
+    ///
+    /// # Return value
+    /// Hash of the created commit.
+    pub fn write_git(
+        self,
+        cat_data: CategoryData,
+        repo: &Repository,
+    ) -> Result<Oid, Box<dyn Error>> {
+        let _span = tracing::info_span!("write_git").entered();
+        let (author, committer) = match self.signatures {
+            Some(signatures) => signatures,
+            None => {
+                let signature = Signature::now(DEFAULT_AUTHOR_NAME, DEFAULT_AUTHOR_EMAIL)?;
+                (signature.clone(), signature)
+            }
+        };
+        let root_h = write_category_tree(repo, &cat_data.graph, cat_data.root, &cat_data.cites)?;
+        let root_t = repo.find_tree(root_h)?;
+        let commit = repo.commit(None, &author, &committer, "init repo", &root_t, &[])?;
+        let c = repo.find_commit(commit)?;
+        repo.branch("master", &c, false)?;
+        Ok(commit)
+    }
+}
+
+/// Main namespace pages with wikitext, which cites are extracted from.
+fn is_article(page: &Page) -> bool {
+    let article = page.namespace == 0
+        && page.format.as_deref() == Some("text/x-wiki")
+        && page.model.as_deref() == Some("wikitext");
+    if !article {
+        tracing::debug!(
+            "Skip {} {} {:?} {:?}",
+            page.namespace,
+            page.title,
+            page.format,
+            page.model
+        );
+    }
+    article
+}
+
+/// Cross edges lead to nodes already reached through another category
+/// (e.g. article in multiple categories).
+fn log_cross_edges(graph: &Graph, edge_types: &HashMap<Ed, EdgeType>) {
+    let mut cross_edges: Vec<Ed> = edge_types
+        .iter()
+        .filter(|(_, edge_type)| **edge_type == EdgeType::Cross)
+        .map(|(e, _)| *e)
+        .collect();
+    cross_edges.sort_unstable();
+    tracing::info!("Found {} cross edges", cross_edges.len());
+    for (from, to) in cross_edges {
+        tracing::debug!(
+            "'{}' is also in '{}'",
+            graph.get_vertex_label(to).0,
+            graph.get_vertex_label(from).0
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{config, DUMP};
+
+    #[test]
+    fn replace_unusual_unicode_normalizes_spaces() {
+        assert_eq!(
+            replace_unusual_unicode("a\u{00A0}b\u{200B}c\u{2028}d\u{0007}"),
+            "a bc\nd"
+        );
+    }
+
+    #[test]
+    fn strip_unmatched_tags_keeps_balanced_tags() {
+        let text = "<b>a</b><br>b<br/><i>c</i><hr>";
+        assert_eq!(strip_unmatched_tags(text), text);
+    }
+
+    #[test]
+    fn strip_unmatched_tags_removes_only_unmatched_tags() {
+        assert_eq!(
+            strip_unmatched_tags("<b>a</b> <b>b<br>c</i> <small>d</small>"),
+            "<b>a</b> b<br>c <small>d</small>"
+        );
+        assert_eq!(
+            strip_unmatched_tags("<div><i>a</div> <i>b</i>"),
+            "<div>a</div> <i>b</i>"
+        );
+    }
+
+    #[test]
+    fn parse_with_retry_parses_stripped_text() {
+        let config = config();
+        let text = "Ala<br>ma <poem>kota";
+        let mut buffers = RetryBuffers::default();
+        let (parsed, warnings, parsed_text) = parse_with_retry(&config, text, &mut buffers);
+
+        assert!(!warnings.is_empty());
+        assert!(parsed.warnings.is_empty());
+        assert_eq!(parsed_text, "Ala<br>ma kota");
+    }
+
+    #[test]
+    fn parse_with_retry_keeps_text_without_warnings() {
+        let config = config();
+        let text = "Ala<br>ma <poem>kota</poem>";
+        let mut buffers = RetryBuffers::default();
+        let (parsed, warnings, parsed_text) = parse_with_retry(&config, text, &mut buffers);
+
+        assert!(warnings.is_empty());
+        assert!(parsed.warnings.is_empty());
+        assert_eq!(parsed_text, text);
+    }
+
+    #[test]
+    fn process_builds_graph_and_extracts_cites() {
+        let cat_data = WikiquoteClient::new(config())
+            .with_graph_capacity(16)
+            .process(DUMP.as_bytes())
+            .unwrap();
+
+        assert_eq!(cat_data.graph.len(), 3);
+        assert_eq!(cat_data.graph.get_vertex_label(cat_data.root).0, "Ludzie");
+        assert!(cat_data.cycles.is_empty());
+        let article = cat_data
+            .graph
+            .find_vertex(&("Adam Mickiewicz".to_string(), false))
+            .unwrap();
+        assert_eq!(cat_data.cites.len(), 1);
+        assert_eq!(cat_data.cites[&article].cites.len(), 2);
+    }
+
+    #[test]
+    fn process_stores_node_attributes() {
+        let cat_data = WikiquoteClient::new(config())
+            .process(DUMP.as_bytes())
+            .unwrap();
+        let graph = &cat_data.graph;
+        let article = graph
+            .find_vertex(&("Adam Mickiewicz".to_string(), false))
+            .unwrap();
+        let poets = graph.find_vertex(&("Poeci".to_string(), true)).unwrap();
+
+        assert_eq!(category_graph::cite_count(graph, article), 2);
+        assert_eq!(
+            graph.get_node_attribute(article, LAST_MODIFIED),
+            Some("2020-05-01T10:00:00Z")
+        );
+        assert_eq!(
+            graph.get_node_attribute(poets, LAST_MODIFIED),
+            Some("2019-03-02T08:30:00Z")
+        );
+        assert_eq!(graph.get_node_attribute(cat_data.root, LAST_MODIFIED), None);
+    }
+
+    #[test]
+    fn searched_root_is_not_contracted() {
+        let cat_data = WikiquoteClient::new(config())
+            .with_search("Poeci")
+            .with_contract_paths(true)
+            .with_prune_unreachable(true)
+            .process(DUMP.as_bytes())
+            .unwrap();
+
+        assert_eq!(cat_data.graph.get_vertex_label(cat_data.root).0, "Poeci");
+        assert_eq!(cat_data.graph.len(), 2);
+    }
+
+    #[test]
+    fn process_reports_progress() {
+        let reports = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let seen = reports.clone();
+        WikiquoteClient::new(config())
+            .with_progress(1, move |p| seen.borrow_mut().push((p.articles, p.cites)))
+            .process(DUMP.as_bytes())
+            .unwrap();
+
+        assert_eq!(*reports.borrow(), vec![(1, 2)]);
+    }
+
+    #[test]
+    fn process_uses_given_graph() {
+        let graph = Graph::from_dot(
+            r#"digraph { "Adam Mickiewicz" [category="false"]; Ludzie -> "Adam Mickiewicz" }"#,
+        )
+        .unwrap();
+        let cat_data = WikiquoteClient::new(config())
+            .with_graph(graph)
+            .process(DUMP.as_bytes())
+            .unwrap();
+
+        assert_eq!(cat_data.graph.len(), 2);
+        let article = cat_data
+            .graph
+            .find_vertex(&("Adam Mickiewicz".to_string(), false))
+            .unwrap();
+        assert_eq!(cat_data.cites[&article].cites.len(), 2);
+    }
+
+    #[test]
+    fn write_git_commits_category_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        let cat_data = WikiquoteClient::new(config())
+            .process(DUMP.as_bytes())
+            .unwrap();
+        let author = Signature::now("Author", "author@example.com").unwrap();
+        let committer = Signature::now("Committer", "committer@example.com").unwrap();
+
+        let commit = WikiquoteClient::new(config())
+            .with_signatures(author, committer)
+            .write_git(cat_data, &repo)
+            .unwrap();
+
+        let repo = Repository::open_bare(dir.path()).unwrap();
+        let commit = repo.find_commit(commit).unwrap();
+        assert_eq!(commit.author().name(), Some("Author"));
+        assert_eq!(commit.committer().email(), Some("committer@example.com"));
+        let tree = commit.tree().unwrap();
+        let cites = tree
+            .get_path(std::path::Path::new("Poeci/Mickiewicz, Adam"))
+            .unwrap()
+            .to_object(&repo)
+            .unwrap()
+            .peel_to_tree()
+            .unwrap();
+        assert!(cites.get_name("1.txt").is_some());
+        assert!(cites.get_name("2.txt").is_some());
+        assert!(repo.find_branch("master", git2::BranchType::Local).is_ok());
+    }
+
+    #[test]
+    fn process_reads_dump_with_other_namespace() {
+        let dump = DUMP.replace(
+            "export-0.10/\" version=\"0.10\"",
+            "export-0.11/\" version=\"0.11\"",
+        );
+        let cat_data = WikiquoteClient::new(config())
+            .process(xml_namespace::XmlNamespaceStripper::new(dump.as_bytes()))
+            .unwrap();
+
+        assert_eq!(cat_data.graph.len(), 3);
+    }
+}
@@ -1,33 +1,46 @@
-mod category_graph;
-use crate::category_graph::{CategoryExtractor, Graph, Normalizer};
-
-mod cite_extractor;
-use cite_extractor::Cites;
-
-mod text_extractor;
-
-use std::collections::HashMap;
+use wikiquote::category_graph::{self, article_count, cite_count, CycleEvent, Graph, Normalizer};
+use wikiquote::debug_json;
+use wikiquote::fetch_config::{build_config, category_namespaces, fetch_site_info, SiteInfo};
+use wikiquote::xml_namespace::XmlNamespaceStripper;
+use wikiquote::{
+    get_git_file_name, log_warnings, parse_with_retry, sanitize_file_name, CategoryData,
+    CiteOptions, Progress, RetryBuffers, WikiquoteClient,
+};
+
+use std::cell::Cell;
 use std::error::Error;
 use std::fmt::Display;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::str::FromStr;
 use std::string::ParseError;
 
-use bit_vec::BitVec;
+use chrono::{DateTime, FixedOffset};
 use collecting_hashmap::CollectingHashMap;
-use git2::{Oid, Repository, Signature};
-use parse_mediawiki_dump::Page;
+use git2::{Repository, Signature};
 use parse_wiki_text::{self, Configuration, ConfigurationSource};
 use radix_fmt::radix_36;
+use regex::RegexBuilder;
 use serde::export::Formatter;
+use serde::Serialize;
 use structopt::StructOpt;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
 
+#[cfg(test)]
 #[macro_use]
 extern crate lazy_static;
 
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "test_util.rs"]
+mod test_util;
+
 // Configuration for pl.wikiquote.org
 // Generated by https://github.com/portstrom/fetch_mediawiki_configuration
-lazy_static! {
-    static ref WIKICONF: Configuration = {
+fn wikiconf() -> Result<Configuration, String> {
+    std::panic::catch_unwind(|| {
         Configuration::new(&ConfigurationSource {
             category_namespaces: &["category", "kategoria"],
             extension_tags: &[
@@ -123,9 +136,20 @@ lazy_static! {
             ],
             redirect_magic_words: &["PATRZ", "PRZEKIERUJ", "REDIRECT", "TAM"],
         })
-    };
+    })
+    .map_err(|panic| {
+        panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown error".to_string())
+    })
 }
 
+// Limits for user supplied search regex, so that it can't blow up
+const SEARCH_REGEX_SIZE_LIMIT: usize = 1 << 20;
+const SEARCH_REGEX_DFA_SIZE_LIMIT: usize = 1 << 20;
+
 #[derive(Debug, PartialEq)]
 enum Command {
     LIST,
@@ -133,6 +157,9 @@ enum Command {
     JSON,
     DEBUG,
     CATS,
+    STATS,
+    VALIDATE,
+    ANKI,
 }
 
 impl FromStr for Command {
@@ -144,11 +171,74 @@ impl FromStr for Command {
             "json" => Ok(Command::JSON),
             "debug" => Ok(Command::DEBUG),
             "cats" => Ok(Command::CATS),
+            "stats" => Ok(Command::STATS),
+            "validate" => Ok(Command::VALIDATE),
+            "anki" => Ok(Command::ANKI),
             _ => Ok(Command::LIST),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+    Markdown,
+    Html,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "markdown" => Ok(OutputFormat::Markdown),
+            "html" => Ok(OutputFormat::Html),
+            _ => Err(format!("Unknown output format '{}'", format)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum DebugFormat {
+    Rust,
+    Json,
+}
+
+impl FromStr for DebugFormat {
+    type Err = String;
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "rust" => Ok(DebugFormat::Rust),
+            "json" => Ok(DebugFormat::Json),
+            _ => Err(format!("Unknown debug format '{}'", format)),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum GraphFormat {
+    Gexf,
+    /// Nodes and edges in separate files.
+    Csv,
+    Gml,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "gexf" => Ok(GraphFormat::Gexf),
+            "csv" => Ok(GraphFormat::Csv),
+            "gml" => Ok(GraphFormat::Gml),
+            _ => Err(format!("Unknown graph format '{}'", format)),
+        }
+    }
+}
+
 #[derive(Debug, StructOpt)]
 #[structopt(name = "wikiquote", about = "Parse wikiquotes.")]
 struct Opt {
@@ -163,120 +253,349 @@ struct Opt {
 
     #[structopt(default_value)]
     search: String,
+
+    /// Treat search as a regular expression matched against article titles
+    #[structopt(long = "search-regex")]
+    search_regex: bool,
+
+    /// Approximate betweenness centrality using K random source nodes
+    #[structopt(long = "centrality-sample")]
+    centrality_sample: Option<usize>,
+
+    /// Also write cites as plain text files under this directory
+    #[structopt(long = "text-output", parse(from_os_str))]
+    text_output: Option<PathBuf>,
+
+    /// Name of the commit author
+    #[structopt(long = "author-name", default_value = "WikiQuotes")]
+    author_name: String,
+
+    /// Email of the commit author (default: anonymous@<email domain>)
+    #[structopt(long = "author-email")]
+    author_email: Option<String>,
+
+    /// Name of the committer
+    #[structopt(
+        long = "committer-name",
+        alias = "commit-name",
+        default_value = "WikiQuotes"
+    )]
+    committer_name: String,
+
+    /// Email of the committer (default: anonymous@<email domain>)
+    #[structopt(long = "committer-email")]
+    committer_email: Option<String>,
+
+    /// Author timestamp of the commit as ISO 8601 date (default: now)
+    ///
+    /// Reproducible build mode: when both --author-date and --committer-date are set
+    /// (e.g. to date of the latest revision in the dump), the same dump gives the same
+    /// commit hash on every machine.
+    #[structopt(long = "author-date", parse(try_from_str = DateTime::parse_from_rfc3339))]
+    author_date: Option<DateTime<FixedOffset>>,
+
+    /// Committer timestamp of the commit as ISO 8601 date (default: now)
+    #[structopt(long = "committer-date", parse(try_from_str = DateTime::parse_from_rfc3339))]
+    committer_date: Option<DateTime<FixedOffset>>,
+
+    /// Domain of default author and committer emails
+    #[structopt(long = "email-domain", default_value = "pl.wikiquote.org")]
+    email_domain: String,
+
+    /// List graph vertices with labels starting with this prefix (validate command)
+    #[structopt(long = "query")]
+    query: Option<String>,
+
+    /// Skip cites without attribution metadata
+    #[structopt(long = "require-attribution")]
+    require_attribution: bool,
+
+    /// Collapse whitespace (including new lines) in cite texts
+    #[structopt(long = "normalize-whitespace")]
+    normalize_whitespace: bool,
+
+    /// Print progress every N articles stored in git
+    #[structopt(long = "progress-interval", default_value = "100")]
+    progress_interval: usize,
+
+    /// Remove templates not handled by the parser from cite texts
+    #[structopt(long = "clean-templates")]
+    clean_templates: bool,
+
+    /// Take paragraphs as cites in articles that have headings but no lists
+    #[structopt(long = "extract-paragraphs")]
+    extract_paragraphs: bool,
+
+    /// Take contents of these tags (e.g. poem) as cites instead of list items
+    #[structopt(long = "tag")]
+    tag: Option<String>,
+
+    /// Fetch parser configuration from MediaWiki API at this URL (e.g. https://pl.wikiquote.org/w/api.php)
+    #[structopt(long = "fetch-config")]
+    fetch_config: Option<String>,
+
+    /// Collapse chains of categories having single parent and single child
+    #[structopt(long = "contract-paths")]
+    contract_paths: bool,
+
+    /// Remove categories having no articles and no subcategories
+    #[structopt(long = "prune-empty")]
+    prune_empty: bool,
+
+    /// Output format: text, json, csv, markdown or html (csv, markdown and html apply to cites only)
+    #[structopt(long = "output-format", default_value = "text")]
+    output_format: OutputFormat,
+
+    /// Format of parser output printed by debug command: rust or json
+    #[structopt(long = "debug-format", default_value = "rust")]
+    debug_format: DebugFormat,
+
+    /// Export category graph in this format (gexf, gml or csv) to file given by --graph-output
+    /// (csv writes <graph-output>.nodes.csv and <graph-output>.edges.csv)
+    #[structopt(long = "graph-format", requires = "graph-output")]
+    graph_format: Option<GraphFormat>,
+
+    /// File to export category graph to
+    #[structopt(long = "graph-output", parse(from_os_str))]
+    graph_output: Option<PathBuf>,
+
+    /// Load category graph from CSV file (from_label,is_from_cat,edge_label,to_label,is_to_cat)
+    /// instead of scanning the dump for categories
+    #[structopt(long = "import-graph", parse(from_os_str))]
+    import_graph: Option<PathBuf>,
 }
 
 fn main() {
     let args: Opt = Opt::from_args();
 
+    // warnings and infos are always shown (as plain messages), RUST_LOG also enables span timings
+    match EnvFilter::try_from_default_env() {
+        Ok(filter) => tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_writer(std::io::stderr)
+            .init(),
+        Err(_) => tracing_subscriber::fmt()
+            .with_env_filter(EnvFilter::new("info"))
+            .without_time()
+            .with_target(false)
+            .with_writer(std::io::stderr)
+            .init(),
+    }
+
     match do_main(args) {
         Ok(()) => {}
         Err(e) => eprintln!("ERROR: {}", e),
     }
 }
 
-struct CategoryData(Graph, category_graph::Nd, BitVec);
-
 fn do_main(args: Opt) -> Result<(), Box<dyn Error>> {
+    let site_info = match &args.fetch_config {
+        Some(api_url) => Some(fetch_site_info(api_url)?),
+        None => None,
+    };
+
     if args.command == Command::CATS {
         let repo = Repository::init_bare(&args.output)?;
-        let cat_data = process_categories(&args, get_reader(&args)?)?;
-        let cite_hashes = add_articles_to_git(&cat_data, get_reader(&args)?, &repo)?;
-        store_categories_in_git(&cat_data, cite_hashes, repo)?;
+        let (reader, bytes_read) = get_reader(&args)?;
+        let total = std::fs::metadata(&args.datafile)?.len();
+        let format = args.output_format;
+        let progress_client = client(&args, site_info.as_ref())?
+            .with_progress(args.progress_interval, move |progress| {
+                print_progress(format, progress, bytes_read.get(), total)
+            });
+        let cat_data = process_dump(&args, progress_client, reader)?;
+        if let (Some(format), Some(path)) = (&args.graph_format, &args.graph_output) {
+            export_graph(&cat_data.graph, format, path)?;
+        }
+        if let Some(dir) = &args.text_output {
+            for (v, cites) in &cat_data.cites {
+                let texts: Vec<String> = cites.into_iter().map(|c| format!("{}", c)).collect();
+                write_text_output(dir, &cat_data.graph, *v, &texts)?;
+            }
+        }
+        store_categories_in_git(&args, client(&args, site_info.as_ref())?, cat_data, &repo)?;
+    } else if args.command == Command::STATS {
+        let cat_data = process_dump(
+            &args,
+            client(&args, site_info.as_ref())?,
+            get_reader(&args)?.0,
+        )?;
+        print_stats(&args, &cat_data)?;
+    } else if args.command == Command::VALIDATE {
+        let cat_data = process_dump(
+            &args,
+            client(&args, site_info.as_ref())?,
+            get_reader(&args)?.0,
+        )?;
+        validate(&args, &cat_data);
     } else {
-        add_articles(&args, get_reader(&args)?)?;
+        add_articles(
+            &args,
+            &client(&args, site_info.as_ref())?,
+            get_reader(&args)?.0,
+        )?;
     }
     Ok(())
 }
 
-fn get_reader(cfg: &Opt) -> Result<Box<dyn std::io::BufRead>, Box<dyn Error>> {
-    let file = std::io::BufReader::new(std::fs::File::open(&cfg.datafile)?);
-
-    let reader: Box<dyn std::io::BufRead> = if cfg.datafile.ends_with(".bz2") {
-        Box::new(std::io::BufReader::new(bzip2::bufread::BzDecoder::new(
-            file,
-        )))
-    } else {
-        Box::new(file)
+/// Pipeline configured by command line options, for the wiki described by
+/// `site_info` (pl.wikiquote.org when it's `None`).
+fn client(args: &Opt, site_info: Option<&SiteInfo>) -> Result<WikiquoteClient, Box<dyn Error>> {
+    let (config, normalizer) = match site_info {
+        Some(site_info) => {
+            let namespaces = category_namespaces(site_info);
+            let prefixes: Vec<&str> = namespaces.iter().map(|n| n.as_str()).collect();
+            (
+                build_config(site_info)?,
+                Normalizer::with_category_prefixes(&prefixes),
+            )
+        }
+        None => (wikiconf().map_err(ConfigError)?, Normalizer::default()),
     };
+    Ok(WikiquoteClient::new(config)
+        .with_normalizer(normalizer)
+        .with_search(&args.search)
+        .with_contract_paths(args.contract_paths)
+        .with_prune_empty(args.prune_empty)
+        // other nodes won't be stored in git anyway
+        .with_prune_unreachable(args.command == Command::CATS)
+        .with_cite_options(CiteOptions {
+            clean_templates: args.clean_templates,
+            extract_paragraphs: args.extract_paragraphs,
+            require_attribution: args.require_attribution,
+            normalize_whitespace: args.normalize_whitespace,
+            tag: args.tag.clone(),
+        }))
+}
 
-    Result::Ok(reader)
+fn export_graph(graph: &Graph, format: &GraphFormat, path: &Path) -> Result<(), Box<dyn Error>> {
+    match format {
+        GraphFormat::Gexf => {
+            let mut w = std::io::BufWriter::new(std::fs::File::create(path)?);
+            graph.export_gexf(&mut w)?;
+            w.flush()?;
+        }
+        GraphFormat::Gml => {
+            let mut w = std::io::BufWriter::new(std::fs::File::create(path)?);
+            graph.export_gml(&mut w)?;
+            w.flush()?;
+        }
+        GraphFormat::Csv => graph.export_csv(
+            &path.with_extension("nodes.csv"),
+            &path.with_extension("edges.csv"),
+        )?,
+    }
+    Ok(())
 }
 
-#[derive(Debug, Default)]
-struct NoRootCategoryError;
+/// Bytes of the dump file read so far.
+type BytesRead = Rc<Cell<u64>>;
 
-impl Display for NoRootCategoryError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "No root category found!")
+/// Reader counting bytes read from `inner`.
+struct CountingReader<R> {
+    inner: R,
+    count: BytesRead,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.set(self.count.get() + n as u64);
+        Ok(n)
     }
 }
 
-impl Error for NoRootCategoryError {}
+fn get_reader(cfg: &Opt) -> Result<(Box<dyn std::io::BufRead>, BytesRead), Box<dyn Error>> {
+    let count = BytesRead::default();
+    let file = std::io::BufReader::new(CountingReader {
+        inner: std::fs::File::open(&cfg.datafile)?,
+        count: count.clone(),
+    });
+
+    let reader: Box<dyn std::io::BufRead> = if cfg.datafile.ends_with(".bz2") {
+        Box::new(XmlNamespaceStripper::new(std::io::BufReader::new(
+            bzip2::bufread::BzDecoder::new(file),
+        )))
+    } else {
+        Box::new(XmlNamespaceStripper::new(file))
+    };
+
+    Result::Ok((reader, count))
+}
 
 #[derive(Debug)]
-struct MediawikiParseError(parse_mediawiki_dump::Error);
+struct ConfigError(String);
 
-impl Display for MediawikiParseError {
+impl Display for ConfigError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Mediawiki parse error: ")?;
-        self.0.fmt(f)
+        write!(f, "Invalid parser configuration: {}", self.0)
     }
 }
 
-impl Error for MediawikiParseError {}
+impl Error for ConfigError {}
 
-fn process_categories(
+fn process_dump(
     args: &Opt,
+    client: WikiquoteClient,
     source: impl std::io::BufRead,
 ) -> Result<CategoryData, Box<dyn Error>> {
-    let mut category_extractor = CategoryExtractor::default();
-    for result in parse_mediawiki_dump::parse(source) {
-        match result {
-            Err(error) => return Err(Box::new(MediawikiParseError(error))),
-            Ok(page) => {
-                let (site_name, is_category) = category_extractor
-                    .normalizer
-                    .normalize_category_name(&page.title);
-                let parsed = WIKICONF.parse(&page.text);
-                category_extractor.set_site(site_name);
-                category_extractor.set_is_category(is_category);
-                category_extractor.extract(&parsed);
-            }
+    let client = match &args.import_graph {
+        Some(path) => {
+            let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+            client.with_graph(Graph::load_from_csv(reader)?)
         }
-    }
-
-    let found_root = if !args.search.is_empty() {
-        let search = (args.search.clone(), true);
-        match category_extractor.graph.find_vertex(&search) {
-            None => {
-                let roots = category_extractor.graph.roots();
-                roots.get(0).map(|x| *x)
+        None => client,
+    };
+    let cat_data = client.process(source)?;
+
+    match args.output_format {
+        // stats report includes cycles, so that its output is a single JSON document
+        OutputFormat::Json if args.command == Command::STATS => {}
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&cat_data.cycles)?),
+        _ => {
+            for c in &cat_data.cycles {
+                println!(
+                    "Found loop between '{}' ({}) and '{}' ({})",
+                    c.from_label, c.from_id, c.to_label, c.to_id
+                );
             }
-            some => some,
         }
-    } else {
-        let roots = category_extractor.graph.roots();
-        roots.get(0).map(|x| *x)
-    };
-
-    if let Some(root) = found_root {
-        let visited = category_extractor
-            .graph
-            .walk_dfs_post_order(root, |_, _| Result::Ok(()))?;
+    }
+    report(args.output_format, &cat_data.graph);
 
-        println!(
-            "Visited {} out of {} nodes.",
-            count_ones(&visited),
-            category_extractor.graph.len()
-        );
+    Result::Ok(cat_data)
+}
 
-        Result::Ok(CategoryData(category_extractor.graph, root, visited))
+/// Print human-readable message about processing to stdout or, when it's used
+/// for JSON output, to stderr.
+fn report(format: OutputFormat, message: impl Display) {
+    if format == OutputFormat::Json {
+        eprintln!("{}", message);
     } else {
-        Result::Err(Box::new(NoRootCategoryError::default()))
+        println!("{}", message);
     }
 }
 
-fn add_articles(args: &Opt, source: impl std::io::BufRead) -> Result<(), Box<dyn Error>> {
+fn add_articles(
+    args: &Opt,
+    client: &WikiquoteClient,
+    source: impl std::io::BufRead,
+) -> Result<(), Box<dyn Error>> {
+    let title_regex = if args.search_regex {
+        Some(
+            RegexBuilder::new(&args.search)
+                .size_limit(SEARCH_REGEX_SIZE_LIMIT)
+                .dfa_size_limit(SEARCH_REGEX_DFA_SIZE_LIMIT)
+                .build()?,
+        )
+    } else {
+        None
+    };
+    let title_matches = |title: &str| match &title_regex {
+        Some(regex) => regex.is_match(title),
+        None => title == args.search,
+    };
     for result in parse_mediawiki_dump::parse(source) {
         match result {
             Err(error) => {
@@ -291,22 +610,40 @@ fn add_articles(args: &Opt, source: impl std::io::BufRead) -> Result<(), Box<dyn
                     );
                 }
 
-                Command::PARSE | Command::JSON => {
-                    if page.title == args.search {
-                        println!(
-                            "{} {} {:?} {:?}",
-                            page.namespace, page.title, page.format, page.model
-                        );
-                        let parsed = WIKICONF.parse(&page.text);
-                        let mut extr = Cites::default();
-                        extr.extract_cites(&parsed, &page.title);
-                        if args.command == Command::PARSE {
-                            for cite in extr.cites {
-                                println!("{}", cite);
-                            }
+                Command::PARSE | Command::JSON | Command::ANKI => {
+                    if title_matches(&page.title) {
+                        let format = if args.command == Command::JSON {
+                            &OutputFormat::Json
                         } else {
-                            let ser = serde_json::to_string_pretty(&extr).unwrap();
-                            println!("{}", ser);
+                            &args.output_format
+                        };
+                        // deck and machine-readable formats must contain only cites
+                        if args.command != Command::ANKI && *format == OutputFormat::Text {
+                            println!(
+                                "{} {} {:?} {:?}",
+                                page.namespace, page.title, page.format, page.model
+                            );
+                        }
+                        let mut buffers = RetryBuffers::default();
+                        let (parsed, warnings, _) =
+                            parse_with_retry(client.config(), &page.text, &mut buffers);
+                        log_warnings(&page.title, &warnings);
+                        let extr = client.page_cites(&parsed, &page.title);
+                        let stdout = std::io::stdout();
+                        let mut out = stdout.lock();
+                        if args.command == Command::ANKI {
+                            write!(out, "{}", extr.to_anki_deck())?;
+                            continue;
+                        }
+                        match format {
+                            OutputFormat::Text => writeln!(out, "{}", extr)?,
+                            OutputFormat::Json => {
+                                extr.to_json_writer(&mut out)?;
+                                writeln!(out)?;
+                            }
+                            OutputFormat::Csv => extr.to_csv_writer(&mut out)?,
+                            OutputFormat::Markdown => extr.to_markdown_writer(&mut out)?,
+                            OutputFormat::Html => extr.to_html_writer(&mut out)?,
                         }
                     }
                 }
@@ -317,8 +654,15 @@ fn add_articles(args: &Opt, source: impl std::io::BufRead) -> Result<(), Box<dyn
                             "{} {} {:?} {:?}",
                             page.namespace, page.title, page.format, page.model
                         );
-                        let parsed = WIKICONF.parse(&page.text);
-                        println!("{:?}\n", parsed);
+                        // show what the parser makes of the page as it is, with its warnings
+                        let parsed = client.config().parse(&page.text);
+                        match args.debug_format {
+                            DebugFormat::Rust => println!("{:?}\n", parsed),
+                            DebugFormat::Json => println!(
+                                "{}\n",
+                                serde_json::to_string_pretty(&debug_json::output_to_json(&parsed))?
+                            ),
+                        }
                     }
                 }
 
@@ -330,121 +674,291 @@ fn add_articles(args: &Opt, source: impl std::io::BufRead) -> Result<(), Box<dyn
     Result::Ok(())
 }
 
-type CiteHashes = CollectingHashMap<category_graph::Nd, Oid>;
+/// Statistics computed here in addition to `Graph::statistics`.
+#[derive(Serialize)]
+struct Stats<'a> {
+    cycles: &'a [CycleEvent],
+    top_centrality: Vec<(&'a str, f64)>,
+    component_sizes: Vec<usize>,
+    degree_sequence: Vec<usize>,
+    in_degree_sequence: Vec<usize>,
+    top_article_counts: Vec<(&'a str, usize)>,
+    top_cite_counts: Vec<(&'a str, usize)>,
+}
 
-fn add_articles_to_git(
-    cat_data: &CategoryData,
-    source: impl std::io::BufRead,
-    repo: &Repository,
-) -> Result<CiteHashes, Box<dyn Error>> {
-    let mut result: CiteHashes = CollectingHashMap::new();
-    let CategoryData(graph, _root, _visited) = cat_data;
-    let normalizer = Normalizer::default();
-    for parsed in parse_mediawiki_dump::parse(source) {
-        match parsed {
-            Err(error) => {
-                eprintln!("Error: {}", error);
-                std::process::exit(1);
-            }
-            Ok(Page {
-                format: p_format,
-                model: p_model,
-                namespace: p_ns,
-                text: p_text,
-                title: p_title,
-            }) => {
-                if p_ns == 0 && p_format.is_some() && p_model.is_some() {
-                    let p_format = p_format.unwrap();
-                    let p_model = p_model.unwrap();
-                    if p_format == "text/x-wiki" && p_model == "wikitext" {
-                        let cat = normalizer.normalize_category_name(&p_title);
-                        if !cat.1 {
-                            if let Some(v) = graph.find_vertex(&cat) {
-                                println!("{}", p_title);
-                                let parsed = WIKICONF.parse(&p_text);
-                                let mut extr = Cites::default();
-                                extr.extract_cites(&parsed, &p_title);
-                                for cite in extr.cites {
-                                    let out = format!("{}", cite);
-                                    let id = repo.blob(out.as_bytes())?;
-                                    result.insert(v, id);
-                                }
-                            }
-                        }
-                    } else {
-                        println!("Skip {} {} {:?} {:?}", p_ns, p_title, p_format, p_model);
-                    }
-                } else {
-                    println!("Skip {} {} {:?} {:?}", p_ns, p_title, p_format, p_model);
-                }
-            }
-        }
-    }
-    Result::Ok(result)
+/// Up to 10 nodes with the highest non-zero `count`, with their labels.
+fn top_counts(graph: &Graph, count: fn(&Graph, category_graph::Nd) -> usize) -> Vec<(&str, usize)> {
+    let mut counts: Vec<(category_graph::Nd, usize)> = (0..graph.len())
+        .map(|n| (n, count(graph, n)))
+        .filter(|(_, c)| *c > 0)
+        .collect();
+    counts.sort_by(|(n1, c1), (n2, c2)| c2.cmp(c1).then(n1.cmp(n2)));
+    counts
+        .into_iter()
+        .take(10)
+        .map(|(n, c)| (graph.get_vertex_label(n).0.as_str(), c))
+        .collect()
 }
 
-fn store_categories_in_git(
-    cat_data: &CategoryData,
-    cite_hashes: CiteHashes,
-    repo: Repository,
-) -> Result<(), Box<dyn Error>> {
-    let CategoryData(graph, root, _visited) = cat_data;
-
-    let mut hashes: HashMap<category_graph::Nd, Oid> = HashMap::new();
-
-    let _visited = graph.walk_dfs_post_order(*root, |n, forbidden| {
-        let v_label = graph.get_vertex_label(n);
-        let name_blob = repo.blob(v_label.0.as_bytes())?;
-        let mut builder = repo.treebuilder(None)?;
-        let blob_name = if v_label.1 { "cat.txt" } else { "art.txt" };
-        builder.insert(blob_name, name_blob, 0o100644)?;
-        let data = &graph.node_data[n];
-        for out in &data.outgoing {
-            if !forbidden.contains(out) {
-                let name = get_git_file_name(&graph, n, *out);
-                let h = hashes.get(out).expect("Children should be already added");
-                builder.insert(name, *h, 0o040000)?;
+fn print_stats(args: &Opt, cat_data: &CategoryData) -> Result<(), Box<dyn Error>> {
+    let CategoryData {
+        graph,
+        root,
+        cycles,
+        ..
+    } = cat_data;
+
+    let centrality = match args.centrality_sample {
+        Some(k) => graph.betweenness_centrality_sampled(k),
+        None => graph.betweenness_centrality(),
+    };
+    let mut ranked: Vec<category_graph::Nd> = (0..graph.len()).collect();
+    ranked.sort_by(|a, b| centrality[*b].partial_cmp(&centrality[*a]).unwrap());
+
+    let stats = Stats {
+        cycles,
+        top_centrality: ranked
+            .into_iter()
+            .take(10)
+            .map(|n| (graph.get_vertex_label(n).0.as_str(), centrality[n]))
+            .collect(),
+        component_sizes: graph
+            .weakly_connected_components()
+            .iter()
+            .map(|c| c.len())
+            .collect(),
+        degree_sequence: graph.degree_sequence(),
+        in_degree_sequence: graph.in_degree_sequence(),
+        top_article_counts: top_counts(graph, article_count),
+        top_cite_counts: top_counts(graph, cite_count),
+    };
+
+    match args.output_format {
+        OutputFormat::Json => {
+            let mut json = Vec::new();
+            graph.export_statistics_json(*root, &mut json)?;
+            let mut report: serde_json::Map<String, serde_json::Value> =
+                serde_json::from_slice(&json)?;
+            if let serde_json::Value::Object(extra) = serde_json::to_value(&stats)? {
+                report.extend(extra);
             }
+            println!("{}", serde_json::to_string_pretty(&report)?);
         }
-        if let Some(cites) = cite_hashes.get_all(&n) {
-            let mut i = 0u32;
-            for c in cites {
-                i += 1;
-                let cname = format!("{}.txt", radix_36(i));
-                builder.insert(cname, *c, 0o100644)?;
+        _ => {
+            let graph_stats = graph.statistics(*root);
+            println!(
+                "Graph has {} nodes and {} edges.",
+                graph_stats.node_count, graph_stats.edge_count
+            );
+            println!(
+                "Depth from '{}': max {}, average {:.2}. Found {} loops.",
+                graph_stats.root_label,
+                graph_stats.max_depth,
+                graph_stats.average_depth,
+                graph_stats.cycle_count
+            );
+            println!("Top nodes by betweenness centrality:");
+            for (label, c) in &stats.top_centrality {
+                println!("{:12.2} {}", c, label);
+            }
+            println!(
+                "Found {} weakly connected components of sizes: {}",
+                stats.component_sizes.len(),
+                join_numbers(&stats.component_sizes)
+            );
+            println!(
+                "Top out-degrees: {}",
+                join_numbers(&stats.degree_sequence[..stats.degree_sequence.len().min(10)])
+            );
+            println!(
+                "Top in-degrees: {}",
+                join_numbers(&stats.in_degree_sequence[..stats.in_degree_sequence.len().min(10)])
+            );
+            println!("Top categories by article count:");
+            for (label, count) in &stats.top_article_counts {
+                println!("{:12} {}", count, label);
+            }
+            println!("Top articles by cite count:");
+            for (label, count) in &stats.top_cite_counts {
+                println!("{:12} {}", count, label);
             }
         }
-        let tree = builder.write()?;
-        hashes.insert(n, tree);
-        Ok(())
-    })?;
+    }
+    Ok(())
+}
 
-    let root_h = hashes.get(&root).unwrap();
-    let root_t = repo.find_tree(*root_h)?;
-    let signature = Signature::now("WikiQuotes", "anonymous@pl.wikiquote.org")?;
-    let commit = repo.commit(None, &signature, &signature, "init repo", &root_t, &[])?;
-    println!("commit is {}", commit.to_string());
+fn join_numbers(numbers: &[usize]) -> String {
+    let numbers: Vec<String> = numbers.iter().map(|n| n.to_string()).collect();
+    numbers.join(", ")
+}
 
-    let c = repo.find_commit(commit)?;
-    repo.branch("master", &c, false)?;
+fn validate(args: &Opt, cat_data: &CategoryData) {
+    let graph = &cat_data.graph;
 
-    Ok(())
+    let mut folded: CollectingHashMap<(String, bool), category_graph::Nd> =
+        CollectingHashMap::new();
+    for (n, (label, is_category)) in graph.node_labels_iter() {
+        folded.insert((label.to_lowercase(), *is_category), n);
+    }
+    for duplicates in folded.values().filter(|d| d.len() > 1) {
+        let labels: Vec<&str> = duplicates
+            .iter()
+            .map(|n| graph.get_vertex_label(*n).0.as_str())
+            .collect();
+        println!("Labels differing only in case: {}", labels.join(" | "));
+    }
+
+    if !graph.is_weakly_connected() {
+        println!("Graph is not connected.");
+    }
+    if graph.is_strongly_connected() && graph.len() > 1 {
+        println!("Graph is strongly connected, so every category is on a loop.");
+    }
+    if graph.is_bipartite().is_none() {
+        println!("Graph is not bipartite (it has an odd cycle when edge direction is ignored).");
+    }
+
+    if let Some(query) = &args.query {
+        for n in graph.find_vertices_by_prefix(query) {
+            let (label, is_category) = graph.get_vertex_label(n);
+            let kind = if *is_category { "cat" } else { "art" };
+            println!("{} {} {}", n, kind, label);
+        }
+    }
 }
 
-fn get_git_file_name(graph: &Graph, from: category_graph::Nd, to: category_graph::Nd) -> String {
-    let el = graph.get_edge_label(&(from, to));
-    let name = if !el.is_empty() {
-        el
+/// Print progress of processing the dump, estimating remaining time from
+/// position in the file of `total` bytes.
+fn print_progress(format: OutputFormat, progress: &Progress, bytes_read: u64, total: u64) {
+    let elapsed = progress.elapsed.as_secs_f64();
+    let remaining = if bytes_read > 0 {
+        elapsed / bytes_read as f64 * total.saturating_sub(bytes_read) as f64
     } else {
-        graph.get_vertex_label(to).0.as_ref()
+        0.0
     };
-    name.replace("/", "-")
+    report(
+        format,
+        format_args!(
+            "Processed {} articles, {} cites in {:.1}s, about {:.0}s remaining",
+            progress.articles, progress.cites, elapsed, remaining
+        ),
+    );
+}
+
+/// Write cites of article `v` as `<dir>/<category>/<article>/<index>.txt`
+/// for every category the article belongs to.
+fn write_text_output(
+    dir: &Path,
+    graph: &Graph,
+    v: category_graph::Nd,
+    cites: &[String],
+) -> Result<(), Box<dyn Error>> {
+    if cites.is_empty() {
+        return Ok(());
+    }
+    for parent in graph.direct_parents(v) {
+        let article_dir = dir
+            .join(sanitize_file_name(&graph.get_vertex_label(*parent).0))
+            .join(get_git_file_name(graph, *parent, v));
+        std::fs::create_dir_all(&article_dir)?;
+        for (i, cite) in cites.iter().enumerate() {
+            let cname = format!("{}.txt", radix_36(i + 1));
+            std::fs::write(article_dir.join(cname), cite)?;
+        }
+    }
+    Ok(())
 }
 
-fn count_ones(visited: &BitVec) -> usize {
-    let mut cnt: usize = 0;
-    for b in visited.blocks() {
-        cnt += b.count_ones() as usize;
+fn git_time(date: &DateTime<FixedOffset>) -> git2::Time {
+    git2::Time::new(date.timestamp(), date.offset().local_minus_utc() / 60)
+}
+
+/// Signature with given time, or current time if it's `None`.
+fn signature(
+    name: &str,
+    email: &str,
+    date: Option<&DateTime<FixedOffset>>,
+) -> Result<Signature<'static>, git2::Error> {
+    match date {
+        Some(date) => Signature::new(name, email, &git_time(date)),
+        None => Signature::now(name, email),
+    }
+}
+
+fn store_categories_in_git(
+    args: &Opt,
+    client: WikiquoteClient,
+    cat_data: CategoryData,
+    repo: &Repository,
+) -> Result<(), Box<dyn Error>> {
+    let default_email = format!("anonymous@{}", args.email_domain);
+    let author_email = args.author_email.as_ref().unwrap_or(&default_email);
+    let committer_email = args.committer_email.as_ref().unwrap_or(&default_email);
+    let author = signature(&args.author_name, author_email, args.author_date.as_ref())?;
+    let committer = signature(
+        &args.committer_name,
+        committer_email,
+        args.committer_date.as_ref(),
+    )?;
+    let commit = client
+        .with_signatures(author, committer)
+        .write_git(cat_data, repo)?;
+    report(args.output_format, format_args!("commit is {}", commit));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::DUMP;
+
+    #[test]
+    fn git_time_keeps_offset() {
+        let args = Opt::from_iter(&[
+            "wikiquote",
+            "-d",
+            "dump.xml",
+            "-o",
+            "out",
+            "--author-date",
+            "2020-09-01T12:00:00+02:00",
+        ]);
+        let time = git_time(args.author_date.as_ref().unwrap());
+        assert_eq!(time.seconds(), 1598954400);
+        assert_eq!(time.offset_minutes(), 120);
+        assert!(args.committer_date.is_none());
+    }
+
+    #[test]
+    fn store_categories_in_git_uses_signatures_from_args() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init_bare(dir.path()).unwrap();
+        let args = Opt::from_iter(&[
+            "wikiquote",
+            "-c",
+            "cats",
+            "-d",
+            "-",
+            "-o",
+            "-",
+            "--author-name",
+            "Author",
+            "--email-domain",
+            "example.com",
+        ]);
+        let cat_data = process_dump(&args, client(&args, None).unwrap(), DUMP.as_bytes()).unwrap();
+        assert_eq!(cat_data.cites.len(), 1);
+
+        store_categories_in_git(&args, client(&args, None).unwrap(), cat_data, &repo).unwrap();
+
+        let head = repo
+            .find_branch("master", git2::BranchType::Local)
+            .unwrap()
+            .get()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(head.author().name(), Some("Author"));
+        assert_eq!(head.author().email(), Some("anonymous@example.com"));
     }
-    cnt
 }
@@ -0,0 +1,105 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::rc::Rc;
+
+/// Tags longer than that can't be `<page>` or `<timestamp>`, so the rest of their names is skipped.
+const MAX_TAG_NAME_LENGTH: usize = 16;
+
+/// Timestamps of pages read so far, in order of pages (`None` when a page has none).
+pub type Timestamps = Rc<RefCell<VecDeque<Option<String>>>>;
+
+enum State {
+    Content,
+    TagName(Vec<u8>),
+    Timestamp(Vec<u8>),
+}
+
+/// Reader passing dump through unchanged, recording timestamp of the last
+/// revision of every page (`parse_mediawiki_dump` doesn't read them).
+///
+/// Dump parser reads ahead, so it's to be used as a queue: take the front
+/// timestamp for every parsed page.
+pub struct TimestampRecorder<R> {
+    inner: R,
+    timestamps: Timestamps,
+    state: State,
+}
+
+impl<R: Read> TimestampRecorder<R> {
+    pub fn new(inner: R, timestamps: Timestamps) -> TimestampRecorder<R> {
+        TimestampRecorder {
+            inner,
+            timestamps,
+            state: State::Content,
+        }
+    }
+
+    fn scan(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            match &mut self.state {
+                State::Content => {
+                    if b == b'<' {
+                        self.state = State::TagName(Vec::new());
+                    }
+                }
+                State::TagName(name) => {
+                    if b == b'>' || b.is_ascii_whitespace() {
+                        match name.as_slice() {
+                            b"page" => self.timestamps.borrow_mut().push_back(None),
+                            b"timestamp" if b == b'>' => {
+                                self.state = State::Timestamp(Vec::new());
+                                continue;
+                            }
+                            _ => {}
+                        }
+                        self.state = State::Content;
+                    } else if name.len() < MAX_TAG_NAME_LENGTH {
+                        name.push(b);
+                    }
+                }
+                State::Timestamp(value) => {
+                    if b == b'<' {
+                        let value = String::from_utf8_lossy(value).trim().to_string();
+                        if let Some(last) = self.timestamps.borrow_mut().back_mut() {
+                            *last = Some(value);
+                        }
+                        self.state = State::TagName(Vec::new());
+                    } else {
+                        value.push(b);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> Read for TimestampRecorder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.scan(&buf[..n]);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_are_recorded_per_page() {
+        let dump = "<mediawiki><page><title>A</title><revision><timestamp> 2020-05-01T10:00:00Z </timestamp>\
+                    <text>&lt;timestamp&gt;x</text></revision></page>\
+                    <page><title>B</title><revision><text/></revision></page></mediawiki>";
+        let timestamps = Timestamps::default();
+        let mut recorder = TimestampRecorder::new(dump.as_bytes(), timestamps.clone());
+        let mut read = String::new();
+        recorder.read_to_string(&mut read).unwrap();
+
+        assert_eq!(read, dump);
+        assert_eq!(
+            timestamps.borrow().iter().cloned().collect::<Vec<_>>(),
+            vec![Some("2020-05-01T10:00:00Z".to_string()), None]
+        );
+    }
+}
@@ -0,0 +1,26 @@
+//! Fixtures shared by tests.
+
+use parse_wiki_text::{Configuration, ConfigurationSource, Output};
+
+/// Dump with article "Adam Mickiewicz" in category "Poeci", which is in "Ludzie".
+pub const DUMP: &str = include_str!("../tests/fixtures/dump.xml");
+
+lazy_static! {
+    static ref CONFIG: Configuration = config();
+}
+
+pub fn config() -> Configuration {
+    Configuration::new(&ConfigurationSource {
+        category_namespaces: &["category", "kategoria"],
+        extension_tags: &["poem", "ref"],
+        file_namespaces: &["file", "plik"],
+        link_trail: "abcdefghijklmnopqrstuvwxyz",
+        magic_words: &[],
+        protocols: &["http://", "https://"],
+        redirect_magic_words: &["PATRZ", "REDIRECT"],
+    })
+}
+
+pub fn parse(wiki_text: &str) -> Output<'_> {
+    CONFIG.parse(wiki_text)
+}
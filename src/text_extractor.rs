@@ -1,15 +1,42 @@
-use parse_wiki_text::{self, DefinitionListItem, ListItem, Node};
+use parse_wiki_text::{self, DefinitionListItem, ListItem, Node, Parameter};
+use regex::Regex;
 
-pub struct TextExtractor {
+lazy_static! {
+    static ref TEMPLATE_MATCH: Regex = Regex::new(r"\{\{[^{}]*\}\}").unwrap();
+}
+
+/// Remove `{{...}}` templates left in text (e.g. `{{PAGENAME}}` or `{{subst:...}}`).
+pub fn strip_template_artifacts(text: &str) -> String {
+    let mut result = text.to_string();
+    // nested templates are removed from the innermost one
+    while TEMPLATE_MATCH.is_match(&result) {
+        result = TEMPLATE_MATCH.replace_all(&result, "").into_owned();
+    }
+    result
+}
+
+/// Expands template with given name and parameters, `None` if it's unknown.
+pub type TemplateResolver = dyn Fn(&str, &[Parameter]) -> Option<String>;
+
+pub struct TextExtractor<'a> {
     pub text: Vec<String>,
     pub descend_lists: bool,
+    /// Templates are skipped, unless this resolves them.
+    pub template_resolver: Option<&'a TemplateResolver>,
 }
 
-impl TextExtractor {
-    pub fn new() -> TextExtractor {
+impl Default for TextExtractor<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> TextExtractor<'a> {
+    pub fn new() -> TextExtractor<'a> {
         TextExtractor {
             text: Vec::new(),
             descend_lists: true,
+            template_resolver: None,
         }
     }
 
@@ -85,6 +112,18 @@ impl TextExtractor {
 
             Node::Text { value, .. } => self.text.push(value.to_string()),
 
+            Node::Template {
+                name, parameters, ..
+            } => {
+                if let Some(resolver) = self.template_resolver {
+                    let mut extr = TextExtractor::new();
+                    extr.extract_nodes_text(name);
+                    if let Some(text) = resolver(extr.result().trim(), parameters) {
+                        self.text.push(text)
+                    }
+                }
+            }
+
             _ => {}
         }
     }
@@ -95,6 +134,39 @@ impl TextExtractor {
         }
     }
 
+    /// Extract text only from inside `tag` tags (or any tag, when `None`).
+    pub fn extract_tagged(&mut self, nodes: &[Node], tag: Option<&str>) {
+        for n in nodes {
+            match n {
+                Node::Tag { name, nodes, .. } => {
+                    if tag.is_none_or(|t| name == t) {
+                        self.extract_nodes_text(nodes)
+                    } else {
+                        self.extract_tagged(nodes, tag)
+                    }
+                }
+
+                Node::DefinitionList { items, .. } => {
+                    for item in items {
+                        self.extract_tagged(&item.nodes, tag)
+                    }
+                }
+
+                Node::UnorderedList { items, .. } | Node::OrderedList { items, .. } => {
+                    for item in items {
+                        self.extract_tagged(&item.nodes, tag)
+                    }
+                }
+
+                Node::Heading { nodes, .. } | Node::Preformatted { nodes, .. } => {
+                    self.extract_tagged(nodes, tag)
+                }
+
+                _ => {}
+            }
+        }
+    }
+
     pub fn extract_dl_text(&mut self, node: &DefinitionListItem) {
         for n in &node.nodes {
             self.extract_node_text(n)
@@ -107,3 +179,57 @@ impl TextExtractor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::parse;
+
+    fn extract_text(wiki_text: &str) -> String {
+        let parsed = parse(wiki_text);
+        let mut extr = TextExtractor::new();
+        extr.extract_nodes_text(&parsed.nodes);
+        extr.result()
+    }
+
+    fn extract_tagged_text(wiki_text: &str, tag: Option<&str>) -> String {
+        let parsed = parse(wiki_text);
+        let mut extr = TextExtractor::new();
+        extr.extract_tagged(&parsed.nodes, tag);
+        extr.result()
+    }
+
+    #[test]
+    fn extract_tagged_takes_only_given_tag() {
+        assert_eq!(
+            extract_tagged_text(
+                "a <poem>b</poem> c <ref>d</ref>\n* <poem>e</poem>",
+                Some("poem")
+            ),
+            "be"
+        );
+    }
+
+    #[test]
+    fn extract_tagged_without_tag_takes_all_tags() {
+        assert_eq!(
+            extract_tagged_text("a <poem>b</poem> c <ref>d</ref>", None),
+            "bd"
+        );
+    }
+
+    #[test]
+    fn nbsp_entity_is_decoded() {
+        assert_eq!(extract_text("a&nbsp;b"), "a\u{a0}b");
+    }
+
+    #[test]
+    fn mdash_entity_is_decoded() {
+        assert_eq!(extract_text("a&mdash;b"), "a\u{2014}b");
+    }
+
+    #[test]
+    fn hellip_entity_is_decoded() {
+        assert_eq!(extract_text("a&hellip;"), "a\u{2026}");
+    }
+}
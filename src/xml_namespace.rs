@@ -0,0 +1,94 @@
+use regex::bytes::Regex;
+use std::io::{self, BufRead, Read};
+
+/// Namespace required by `parse_mediawiki_dump`.
+const MEDIAWIKI_NAMESPACE: &[u8] = b"http://www.mediawiki.org/xml/export-0.10/";
+
+/// Start tags longer than that are passed through unchanged.
+const MAX_HEAD_LENGTH: usize = 1 << 16;
+
+lazy_static! {
+    static ref XMLNS_MATCH: Regex = Regex::new(r#"\s+xmlns\s*=\s*"[^"]*""#).unwrap();
+}
+
+/// Reader removing `xmlns="..."` attribute from the root `<mediawiki>` element.
+///
+/// `parse_mediawiki_dump` only accepts dumps declaring export schema 0.10, so
+/// after the declared namespace (e.g. of schema 0.11, or none at all) is removed
+/// the one it expects is put in its place. The rest of the stream is not changed.
+pub struct XmlNamespaceStripper<R> {
+    inner: R,
+    head: Vec<u8>,
+    pos: usize,
+    started: bool,
+}
+
+impl<R: BufRead> XmlNamespaceStripper<R> {
+    pub fn new(inner: R) -> XmlNamespaceStripper<R> {
+        XmlNamespaceStripper {
+            inner,
+            head: Vec::new(),
+            pos: 0,
+            started: false,
+        }
+    }
+
+    /// Read everything up to the end of `<mediawiki ...>` tag and fix its attributes.
+    fn read_head(&mut self) -> io::Result<()> {
+        self.started = true;
+        let mut tag_start = 0;
+        loop {
+            let n = self.inner.read_until(b'>', &mut self.head)?;
+            let chunk = &self.head[tag_start..];
+            if let Some(i) = find(chunk, b"<mediawiki") {
+                let i = tag_start + i;
+                let tag = &self.head[i..];
+                let mut fixed = self.head[..i].to_vec();
+                fixed.extend_from_slice(b"<mediawiki xmlns=\"");
+                fixed.extend_from_slice(MEDIAWIKI_NAMESPACE);
+                fixed.push(b'"');
+                fixed.extend_from_slice(&XMLNS_MATCH.replace_all(&tag[10..], &b""[..]));
+                self.head = fixed;
+                return Ok(());
+            }
+            if n == 0 || self.head.len() > MAX_HEAD_LENGTH {
+                return Ok(());
+            }
+            tag_start = self.head.len();
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+impl<R: BufRead> Read for XmlNamespaceStripper<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for XmlNamespaceStripper<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if !self.started {
+            self.read_head()?;
+        }
+        if self.pos < self.head.len() {
+            return Ok(&self.head[self.pos..]);
+        }
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if self.pos < self.head.len() {
+            self.pos += amt;
+        } else {
+            self.inner.consume(amt);
+        }
+    }
+}